@@ -1,22 +1,23 @@
+mod animation;
+mod clipboard;
+mod history;
+
 use std::borrow::Cow;
-use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::ops::Deref;
 
-use eframe::egui::{self, Context, DragValue, Key, TextEdit, Ui};
-use eframe::{Frame, Storage};
+use eframe::egui::{self, vec2, Context, DragValue, Key, Pos2, TextEdit, Ui};
+use eframe::Frame;
+#[cfg(feature = "persistence")]
+use eframe::Storage;
 use instant::Instant;
-use log::info;
+use log::{debug, info, warn};
 
 use egui_node_graph::*;
 
-use crate::app::node::structs::*;
-use crate::app::wzrd_node_graph::*;
-
-mod node;
-pub mod wzrd_node_graph;
-
-use crate::app::node::WzrdNodes;
+use crate::app::animation::WzrdLayoutAnimation;
+use crate::app::history::WzrdHistory;
+use crate::graph_core::node::structs::*;
+use crate::graph_core::wzrd_node_graph::*;
 #[cfg(target_arch = "wasm32")]
 use eframe::wasm_bindgen::{self, prelude::*};
 
@@ -25,71 +26,55 @@ use eframe::wasm_bindgen::{self, prelude::*};
 extern "C" {
     #[wasm_bindgen(js_name = "updateDocument")]
     pub fn update_document(document: &str);
-}
 
-impl Default for WzrdValueType {
-    fn default() -> Self {
-        Self::Float { value: 0.0 }
-    }
+    #[wasm_bindgen(catch, js_name = "openDefinition")]
+    pub fn open_definition(path: &str, line: u32) -> Result<(), JsValue>;
+
+    /// The document as it is after an edit in the text editor, nothing if it is unchanged since
+    /// the last call.
+    #[wasm_bindgen(catch, js_name = "takeDocumentChange")]
+    pub fn take_document_change() -> Result<Option<String>, JsValue>;
 }
 
 impl DataTypeTrait<WzrdGraphState> for WzrdNodeDataType {
-    fn data_type_color(&self, user_state: &mut WzrdGraphState) -> ecolor::Color32 {
+    fn data_type_color(&self, _user_state: &mut WzrdGraphState) -> ecolor::Color32 {
         match self {
             WzrdNodeDataType::Number => ecolor::Color32::from_rgb(0, 0, 255),
+            WzrdNodeDataType::Integer => ecolor::Color32::from_rgb(70, 110, 255),
+            WzrdNodeDataType::Float => ecolor::Color32::from_rgb(0, 170, 255),
+            WzrdNodeDataType::Boolean => ecolor::Color32::from_rgb(220, 140, 40),
+            WzrdNodeDataType::String => ecolor::Color32::from_rgb(90, 180, 90),
+            WzrdNodeDataType::Nil => ecolor::Color32::from_rgb(120, 120, 120),
+            WzrdNodeDataType::Symbol => ecolor::Color32::from_rgb(200, 80, 160),
+            WzrdNodeDataType::Range => ecolor::Color32::from_rgb(60, 170, 170),
+            WzrdNodeDataType::Regexp => ecolor::Color32::from_rgb(200, 60, 60),
+            WzrdNodeDataType::Expression => ecolor::Color32::from_rgb(170, 120, 220),
             _ => ecolor::Color32::from_rgb(205, 205, 205),
         }
     }
 
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         match self {
             WzrdNodeDataType::Number => Cow::Borrowed("number"),
+            WzrdNodeDataType::Integer => Cow::Borrowed("integer"),
+            WzrdNodeDataType::Float => Cow::Borrowed("float"),
+            WzrdNodeDataType::Boolean => Cow::Borrowed("boolean"),
+            WzrdNodeDataType::String => Cow::Borrowed("string"),
+            WzrdNodeDataType::Expression => Cow::Borrowed("expression"),
+            WzrdNodeDataType::Nil => Cow::Borrowed("nil"),
+            WzrdNodeDataType::Symbol => Cow::Borrowed("symbol"),
+            WzrdNodeDataType::Range => Cow::Borrowed("range"),
+            WzrdNodeDataType::Regexp => Cow::Borrowed("regexp"),
             WzrdNodeDataType::Any => Cow::Borrowed("any"),
-            _ => Cow::Borrowed("Still don't know what this does."),
+            WzrdNodeDataType::None => Cow::Borrowed("none"),
         }
     }
 }
 
-impl NodeTemplateTrait for WzrdNode {
-    type NodeData = WzrdNodeData;
-    type DataType = WzrdNodeDataType;
-    type ValueType = WzrdValueType;
-    type UserState = WzrdGraphState;
-
-    fn node_finder_label(&self, user_state: &mut Self::UserState) -> Cow<str> {
-        Cow::Borrowed(&self.label)
-    }
-
-    fn node_graph_label(&self, user_state: &mut Self::UserState) -> String {
-        self.node_finder_label(user_state).into()
-    }
-
-    fn user_data(&self, user_state: &mut Self::UserState) -> Self::NodeData {
-        WzrdNodeData {
-            template: self.clone(),
-        }
-    }
-
-    fn build_node(
-        &self,
-        graph: &mut Graph<Self::NodeData, Self::DataType, Self::ValueType>,
-        user_state: &mut Self::UserState,
-        node_id: NodeId,
-    ) {
-        for input in self.inputs.clone() {
-            graph.add_input_param(
-                node_id,
-                input.name,
-                input.data_type.clone().into(),
-                input.data_type,
-                InputParamKind::ConnectionOnly,
-                true,
-            );
-        }
-
-        for output in self.outputs.clone() {
-            graph.add_output_param(node_id, output.name, output.data_type.into());
-        }
+fn socket_label(param_name: &str, data_type: &WzrdNodeDataType) -> String {
+    match data_type {
+        WzrdNodeDataType::Any | WzrdNodeDataType::None => param_name.into(),
+        _ => format!("{param_name}: {:}", data_type.name()),
     }
 }
 
@@ -103,26 +88,98 @@ impl WidgetValueTrait for WzrdValueType {
         param_name: &str,
         node_id: NodeId,
         ui: &mut egui::Ui,
-        user_state: &mut Self::UserState,
-        node_data: &Self::NodeData,
+        _user_state: &mut Self::UserState,
+        _node_data: &Self::NodeData,
     ) -> Vec<Self::Response> {
+        let label = socket_label(param_name, &self.clone().into());
+        let mut changed = false;
         match self {
-            WzrdValueType::Integer { value } | WzrdValueType::Number { value } => {
-                ui.label(param_name);
+            WzrdValueType::Integer { value } => {
+                ui.label(label);
                 ui.horizontal(|ui| {
-                    ui.add(DragValue::new(value));
+                    changed |= ui.add(DragValue::new(value)).changed();
+                });
+            }
+            WzrdValueType::Number { value, decimal } => {
+                ui.label(label);
+                ui.horizontal(|ui| {
+                    if *decimal {
+                        changed |= ui
+                            .add(DragValue::new(value).speed(0.1).min_decimals(1))
+                            .changed();
+                    } else {
+                        changed |= ui.add(DragValue::new(value).max_decimals(0)).changed();
+                    }
+                    let toggle = if *decimal { "1.0" } else { "1" };
+                    if ui
+                        .small_button(toggle)
+                        .on_hover_text("Switch between integer and decimal")
+                        .clicked()
+                    {
+                        *decimal = !*decimal;
+                        if !*decimal {
+                            *value = value.round();
+                        }
+                        changed = true;
+                    }
                 });
             }
             WzrdValueType::Float { value } => {
-                ui.label(param_name);
+                ui.label(label);
                 ui.horizontal(|ui| {
-                    ui.add(DragValue::new(value));
+                    changed |= ui
+                        .add(DragValue::new(value).speed(0.1).min_decimals(1))
+                        .changed();
                 });
             }
             WzrdValueType::String { value } | WzrdValueType::Expression { value } => {
-                ui.label(param_name);
+                ui.label(label);
                 ui.horizontal(|ui| {
-                    ui.add(TextEdit::singleline(value));
+                    changed |= ui.add(TextEdit::singleline(value)).changed();
+                });
+            }
+            WzrdValueType::Boolean { value } => {
+                changed |= ui.checkbox(value, label).changed();
+            }
+            WzrdValueType::Nil => {
+                ui.label(format!("{label} = nil"));
+            }
+            WzrdValueType::Symbol { value } => {
+                ui.label(label);
+                ui.horizontal(|ui| {
+                    ui.label(":");
+                    changed |= ui.add(TextEdit::singleline(value)).changed();
+                });
+            }
+            WzrdValueType::Range {
+                start,
+                end,
+                exclusive,
+            } => {
+                ui.label(label);
+                ui.horizontal(|ui| {
+                    changed |= ui.add(DragValue::new(start)).changed();
+                    let operator = if *exclusive { "..." } else { ".." };
+                    if ui
+                        .small_button(operator)
+                        .on_hover_text("Toggle whether the end is included")
+                        .clicked()
+                    {
+                        *exclusive = !*exclusive;
+                        changed = true;
+                    }
+                    changed |= ui.add(DragValue::new(end)).changed();
+                });
+            }
+            WzrdValueType::Regexp { value, options } => {
+                ui.label(label);
+                ui.horizontal(|ui| {
+                    ui.label("/");
+                    changed |= ui.add(TextEdit::singleline(value)).changed();
+                    ui.label("/");
+                    changed |= ui
+                        .add(TextEdit::singleline(options).desired_width(30.0))
+                        .changed();
                 });
             }
             _ => {
@@ -130,12 +187,28 @@ impl WidgetValueTrait for WzrdValueType {
             }
         }
 
-        Vec::new()
+        if changed {
+            vec![WzrdResponse::ValueChanged(node_id)]
+        } else {
+            Vec::new()
+        }
     }
 }
 
 impl UserResponseTrait for WzrdResponse {}
 
+/// Whether `response` adds, removes or connects nodes. Selecting and moving nodes does not.
+fn changes_graph(response: &NodeResponse<WzrdResponse, WzrdNodeData>) -> bool {
+    matches!(
+        response,
+        NodeResponse::ConnectEventEnded { .. }
+            | NodeResponse::CreatedNode(_)
+            | NodeResponse::DeleteNodeFull { .. }
+            | NodeResponse::DisconnectEvent { .. }
+            | NodeResponse::User(WzrdResponse::OpenSubgraph(_) | WzrdResponse::ExpandSubgraph(_))
+    )
+}
+
 impl NodeDataTrait for WzrdNodeData {
     type Response = WzrdResponse;
     type UserState = WzrdGraphState;
@@ -146,14 +219,25 @@ impl NodeDataTrait for WzrdNodeData {
         &self,
         ui: &mut Ui,
         node_id: NodeId,
-        graph: &Graph<Self, Self::DataType, Self::ValueType>,
-        user_state: &mut Self::UserState,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
     ) -> Vec<NodeResponse<Self::Response, Self>>
     where
         WzrdResponse: UserResponseTrait,
     {
         let mut responses = vec![];
 
+        if self.subgraph.is_some() {
+            ui.horizontal(|ui| {
+                if ui.button("Open").clicked() {
+                    responses.push(NodeResponse::User(WzrdResponse::OpenSubgraph(node_id)));
+                }
+                if ui.button("Expand").clicked() {
+                    responses.push(NodeResponse::User(WzrdResponse::ExpandSubgraph(node_id)));
+                }
+            });
+        }
+
         responses
     }
 
@@ -161,96 +245,673 @@ impl NodeDataTrait for WzrdNodeData {
         &self,
         ui: &mut Ui,
         node_id: NodeId,
-        graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
         user_state: &mut Self::UserState,
     ) -> Vec<NodeResponse<Self::Response, Self>>
     where
         Self::Response: UserResponseTrait,
     {
-        vec![]
+        let mut responses = vec![];
+
+        if self.template.category.as_deref() == Some(PROJECT) {
+            if let Some(method) = user_state.project_methods.get(&self.template.label) {
+                let location = format!(
+                    "{:}:{:}",
+                    method.path.as_deref().unwrap_or("this file"),
+                    method.line
+                );
+                if ui
+                    .small_button("↗")
+                    .on_hover_text(format!("Go to definition ({location})"))
+                    .clicked()
+                {
+                    responses.push(NodeResponse::User(WzrdResponse::OpenDefinition(node_id)));
+                }
+            }
+        }
+
+        responses
     }
 
     fn output_ui(
         &self,
         ui: &mut Ui,
-        _node_id: NodeId,
-        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        node_id: NodeId,
+        graph: &Graph<Self, Self::DataType, Self::ValueType>,
         _user_state: &mut Self::UserState,
         param_name: &str,
     ) -> Vec<NodeResponse<Self::Response, Self>>
     where
         Self::Response: UserResponseTrait,
     {
-        ui.label(param_name);
+        let inferred = graph[node_id]
+            .outputs
+            .iter()
+            .find(|(name, _)| name == param_name)
+            .map(|(_, output_id)| &graph.outputs[*output_id].typ);
+        match inferred {
+            Some(data_type) => ui.label(socket_label(param_name, data_type)),
+            None => ui.label(param_name),
+        };
         vec![]
     }
 
-    fn can_delete(
+    fn titlebar_color(
         &self,
+        _ui: &Ui,
         _node_id: NodeId,
         _graph: &Graph<Self, Self::DataType, Self::ValueType>,
         _user_state: &mut Self::UserState,
+    ) -> Option<ecolor::Color32> {
+        self.template
+            .color
+            .map(|[r, g, b]| ecolor::Color32::from_rgb(r, g, b))
+    }
+
+    /// Only connections that fit as they are get made while dragging, other ones are refused or
+    /// wait for a conversion node, see [`WzrdConnectionCheck`].
+    fn can_connect(
+        &self,
+        output: OutputId,
+        input: InputId,
+        graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
     ) -> bool {
-        false
+        WzrdConnectionCheck::new(graph, output, input) == WzrdConnectionCheck::Valid
+    }
+
+    /// The result of the method and the nodes reading its parameters give the graph its shape,
+    /// the last one of each has to stay.
+    fn can_delete(
+        &self,
+        node_id: NodeId,
+        graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        user_state: &mut Self::UserState,
+    ) -> bool {
+        let count = |same: &dyn Fn(&WzrdNode) -> bool| {
+            graph
+                .nodes
+                .iter()
+                .filter(|(_, node)| same(&node.user_data.template))
+                .count()
+        };
+        let template = &self.template;
+        let parameter = template
+            .outputs
+            .first()
+            .filter(|_| template.label == "Variable")
+            .map(|variable| &variable.name)
+            .filter(|name| user_state.parameters.contains(name));
+
+        if template.label == "output" {
+            count(&|other| other.label == "output") > 1
+        } else if let Some(parameter) = parameter {
+            // an orphaned parameter is dropped from the generated code anyway
+            let is_orphan = !graph.connections.values().any(|output_id| {
+                graph[node_id]
+                    .outputs
+                    .iter()
+                    .any(|(_, output)| output == output_id)
+            });
+            is_orphan
+                || count(&|other| {
+                    other.label == "Variable"
+                        && other.outputs.first().map(|variable| &variable.name) == Some(parameter)
+                }) > 1
+        } else {
+            true
+        }
+    }
+}
+
+/// The egui editor around a [`WzrdNodeGraph`]. It holds what only matters while editing: the
+/// undo history, the running layout animation, the conversion waiting for the user and when
+/// the document was last updated and to what.
+pub struct WzrdApp {
+    pub graph: WzrdNodeGraph,
+    history: WzrdHistory,
+    layout_animation: Option<WzrdLayoutAnimation>,
+    pending_conversion: Option<WzrdPendingConversion>,
+    last_update: Option<Instant>,
+    /// The document as the editor last wrote it, so the host reporting that edit back is not
+    /// taken for an edit of the text.
+    last_document: Option<String>,
+    last_event: Option<Instant>,
+    /// Set when the graph changed, the socket types are inferred again before the next frame
+    types_outdated: bool,
+}
+
+impl WzrdApp {
+    pub fn new(graph: WzrdNodeGraph) -> Self {
+        Self {
+            graph,
+            history: WzrdHistory::default(),
+            layout_animation: None,
+            pending_conversion: None,
+            last_update: None,
+            last_document: None,
+            last_event: None,
+            types_outdated: true,
+        }
+    }
+
+    fn go_to_definition(&self, node_id: NodeId) {
+        let label = &self.graph.state.graph[node_id].user_data.template.label;
+        if let Some(method) = self.graph.user_state.project_methods.get(label) {
+            info!(
+                "definition of {label} is at {:?}:{:}",
+                method.path, method.line
+            );
+            #[cfg(target_arch = "wasm32")]
+            if let Err(err) =
+                open_definition(method.path.as_deref().unwrap_or(""), method.line as u32)
+            {
+                warn!("the host could not open the definition: {err:?}");
+            }
+        }
+    }
+
+    /// Sends the regenerated code as one edit right away, instead of waiting for the cooldown.
+    /// A graph that can't be generated leaves the document as it is.
+    fn update_document_now(&mut self) {
+        let document = match self.graph.evaluate_document() {
+            Ok(document) => document,
+            Err(err) => {
+                warn!("not updating the document: {err}");
+                return;
+            }
+        };
+        debug!("{document}");
+        #[cfg(target_arch = "wasm32")]
+        update_document(&document);
+        self.last_update = Some(Instant::now());
+        self.last_document = Some(document);
+    }
+
+    /// Rebuilds the graph after the document was edited in the text editor, see
+    /// [`WzrdNodeGraph::reimport`].
+    pub fn document_changed(&mut self, code: &str) {
+        if self.last_document.as_deref() == Some(code) {
+            return;
+        }
+        self.layout_animation = None;
+        self.pending_conversion = None;
+        self.graph.reimport(code);
+        self.last_document = Some(code.into());
+        self.types_outdated = true;
+        self.history.mark_changed();
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "persistence"))]
+    fn open_graph_dialog(&mut self) {
+        let path = match rfd::FileDialog::new()
+            .add_filter(GRAPH_FILE_FILTER, &["json"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+        let opened = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| WzrdGraphFile::from_json(&json));
+        match opened {
+            Ok(file) => {
+                self.pending_conversion = None;
+                self.animated(|graph| graph.open_graph_file(file));
+                self.last_event = Some(Instant::now());
+            }
+            Err(err) => warn!("could not open {:}: {err:#}", path.display()),
+        }
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "persistence"))]
+    fn save_graph_dialog(&self) {
+        let path = match rfd::FileDialog::new()
+            .add_filter(GRAPH_FILE_FILTER, &["json"])
+            .set_file_name("graph.json")
+            .save_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+        let saved = self
+            .graph
+            .to_json()
+            .and_then(|json| Ok(std::fs::write(&path, json)?));
+        match saved {
+            Ok(()) => info!("saved the graph to {:}", path.display()),
+            Err(err) => warn!("could not save {:}: {err:#}", path.display()),
+        }
+    }
+
+    fn can_inline(&self) -> bool {
+        let state = &self.graph.state;
+        match state.selected_nodes.as_slice() {
+            [node_id] => self
+                .graph
+                .user_state
+                .project_methods
+                .contains_key(&state.graph[*node_id].user_data.template.label),
+            _ => false,
+        }
+    }
+
+    /// Lays the whole graph out and centers the view on it.
+    fn format_graph(&mut self) {
+        self.animated(|graph| graph.format_graph());
+        let state = &mut self.graph.state;
+        state.pan_zoom.pan = vec2(state.ui_rect.width() / 2.0, state.ui_rect.height() / 2.0);
     }
+
+    fn delete_selection(&mut self) -> Vec<NodeResponse<WzrdResponse, WzrdNodeData>> {
+        let selection = self.graph.state.selected_nodes.clone();
+        self.graph.delete_nodes(&selection)
+    }
+}
+
+/// The layout saved in `storage` for the document `code` when the editor was last closed.
+#[cfg(feature = "persistence")]
+pub fn stored_layout(storage: Option<&dyn Storage>, code: &str) -> Option<WzrdSavedLayout> {
+    storage.and_then(|storage| eframe::get_value(storage, &WzrdNodeGraph::persistence_key(code)))
 }
 
+#[cfg(target_arch = "wasm32")]
 const EXTERNAL_UPDATE_COOLDOWN_MS: u128 = 1000;
-impl eframe::App for WzrdNodeGraph {
+/// How often the host is asked for edits of the document while nothing else repaints the editor.
+#[cfg(target_arch = "wasm32")]
+const DOCUMENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+#[cfg(all(not(target_arch = "wasm32"), feature = "persistence"))]
+const GRAPH_FILE_FILTER: &str = "Wzrd graph";
+impl eframe::App for WzrdApp {
     #[cfg(feature = "persistence")]
-    fn save(&mut self, _storage: &mut dyn Storage) {
-        eframe::set_value(_storage, PERSISTENCE_KEY, &self.state);
+    fn save(&mut self, storage: &mut dyn Storage) {
+        // a subgraph tab holds only part of the document
+        if self.graph.open_subgraphs.is_empty() {
+            let document = match self.graph.evaluate_graph(&mut HashMap::new()) {
+                Ok(document) => document,
+                Err(err) => {
+                    warn!("not saving the layout: {err}");
+                    return;
+                }
+            };
+            eframe::set_value(
+                storage,
+                &WzrdNodeGraph::persistence_key(&document),
+                &self.graph.saved_layout(),
+            );
+        }
     }
 
-    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            // hosts that do not report edits of the text leave the graph as it is
+            if let Some(code) = take_document_change().ok().flatten() {
+                self.document_changed(&code);
+            }
+            ctx.request_repaint_after(DOCUMENT_POLL_INTERVAL);
+        }
+        if self.types_outdated {
+            self.graph.infer_types();
+            self.types_outdated = false;
+        }
+        let last_event = self.last_event;
+        // while a subgraph tab is open the editor only holds the nodes of that subgraph
+        let in_subgraph = !self.graph.open_subgraphs.is_empty();
+        let orphan_count = if in_subgraph {
+            0
+        } else {
+            self.graph.find_orphan_nodes().len()
+        };
+        let template_errors = self.graph.template_errors.clone();
+        let unsupported_code = self.graph.unsupported_code.clone();
+        let tab_count = self.graph.open_subgraphs.len();
+        let has_selection = !self.graph.state.selected_nodes.is_empty();
+        let mut close_tabs_to: Option<usize> = None;
+        let mut collapse_requested = false;
+        let mut extract_requested = false;
+        let mut inline_requested = false;
+        let can_inline = !in_subgraph && self.can_inline();
+        let mut undo_requested = false;
+        let mut redo_requested = false;
+        let mut copy_requested = false;
+        let mut duplicate_requested = false;
+        let mut format_requested = false;
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 egui::widgets::global_dark_light_mode_switch(ui);
+                #[cfg(all(not(target_arch = "wasm32"), feature = "persistence"))]
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open graph…").clicked() {
+                        ui.close_menu();
+                        self.open_graph_dialog();
+                    }
+                    if ui.button("Save graph…").clicked() {
+                        ui.close_menu();
+                        self.save_graph_dialog();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    if ui
+                        .add_enabled(
+                            !in_subgraph && self.history.can_undo(),
+                            egui::Button::new("Undo").shortcut_text("Ctrl+Z"),
+                        )
+                        .clicked()
+                    {
+                        undo_requested = true;
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            !in_subgraph && self.history.can_redo(),
+                            egui::Button::new("Redo").shortcut_text("Ctrl+Shift+Z"),
+                        )
+                        .clicked()
+                    {
+                        redo_requested = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Copy").shortcut_text("Ctrl+C"),
+                        )
+                        .clicked()
+                    {
+                        copy_requested = true;
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Duplicate").shortcut_text("Ctrl+D"),
+                        )
+                        .clicked()
+                    {
+                        duplicate_requested = true;
+                        ui.close_menu();
+                    }
+                });
+                if ui
+                    .add(egui::Button::new("Format").shortcut_text("Shift+F"))
+                    .clicked()
+                {
+                    format_requested = true;
+                }
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Collapse selection"))
+                    .clicked()
+                {
+                    collapse_requested = true;
+                }
+                if ui
+                    .add_enabled(
+                        !in_subgraph && has_selection,
+                        egui::Button::new("Extract method"),
+                    )
+                    .clicked()
+                {
+                    extract_requested = true;
+                }
+                if ui
+                    .add_enabled(can_inline, egui::Button::new("Inline method"))
+                    .clicked()
+                {
+                    inline_requested = true;
+                }
+                if tab_count > 0 {
+                    ui.separator();
+                    if ui.selectable_label(false, "main").clicked() {
+                        close_tabs_to = Some(0);
+                    }
+                    for depth in 1..=tab_count {
+                        ui.label("›");
+                        if ui
+                            .selectable_label(depth == tab_count, format!("{SUBGRAPH} {depth}"))
+                            .clicked()
+                        {
+                            close_tabs_to = Some(depth);
+                        }
+                    }
+                }
+                if orphan_count > 0 {
+                    ui.label(format!(
+                        "⚠ {orphan_count} unconnected node(s) are dropped from the code"
+                    ));
+                }
+                if !unsupported_code.is_empty() {
+                    ui.label("⚠ the document has code the graph can't show, it is not written")
+                        .on_hover_text(unsupported_code.join("\n"));
+                }
+                if !template_errors.is_empty() {
+                    ui.label(format!(
+                        "⚠ {} invalid custom node(s)",
+                        template_errors.len()
+                    ))
+                    .on_hover_text(template_errors.join("\n"));
+                }
             })
         });
 
-        let graph_response: WzrdGraphResponse = egui::CentralPanel::default()
+        if self.animate_layout() {
+            ctx.request_repaint();
+        }
+
+        let graph = &mut self.graph;
+        let mut graph_response: WzrdGraphResponse = egui::CentralPanel::default()
             .show(ctx, |ui| {
-                self.state
-                    .draw_graph_editor(ui, self.node_templates.clone(), &mut self.user_state)
+                graph.state.draw_graph_editor(
+                    ui,
+                    graph.node_templates.clone(),
+                    &mut graph.user_state,
+                )
             })
             .inner;
 
-        ctx.input(|i| {
-            if i.key_released(Key::Delete) {
-                for node_id in self.state.selected_nodes.iter() {
-                    let (node, disc_events) = self.state.graph.remove_node(*node_id);
-                    // Pass the disconnection responses first so user code can perform cleanup
-                    // before node removal response.
-                    self.state.node_positions.remove(*node_id);
-                    // Make sure to not leave references to old nodes hanging
-                    self.state.node_order.retain(|id| *id != *node_id);
+        // text fields have an undo of their own
+        if !in_subgraph && !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                if i.modifiers.command && i.key_pressed(Key::Z) {
+                    if i.modifiers.shift {
+                        redo_requested = true;
+                    } else {
+                        undo_requested = true;
+                    }
+                }
+            });
+        }
+        let restored = (undo_requested && self.undo()) || (redo_requested && self.redo());
+        if restored {
+            self.last_event = Some(Instant::now());
+        }
+
+        if !ctx.wants_keyboard_input() {
+            let cursor = ctx
+                .input(|i| i.pointer.hover_pos())
+                .map(|position| position - self.graph.state.pan_zoom.pan);
+            let events = ctx.input(|i| i.events.clone());
+            for event in events {
+                match event {
+                    egui::Event::Copy => {
+                        if let Some(text) = self.copy_selection() {
+                            ctx.output_mut(|o| o.copied_text = text);
+                        }
+                    }
+                    egui::Event::Cut => {
+                        if let Some((text, mut responses)) = self.cut_selection() {
+                            ctx.output_mut(|o| o.copied_text = text);
+                            graph_response.node_responses.append(&mut responses);
+                        }
+                    }
+                    egui::Event::Paste(text) => {
+                        let position = cursor.unwrap_or(Pos2::new(100.0, 100.0));
+                        self.paste(&text, position);
+                        self.last_event = Some(Instant::now());
+                    }
+                    _ => {}
                 }
             }
-        });
+            if ctx.input(|i| i.modifiers.command && i.key_pressed(Key::D)) {
+                duplicate_requested = true;
+            }
+            if ctx.input(|i| i.modifiers.shift && i.key_pressed(Key::F)) {
+                format_requested = true;
+            }
+        }
+        if copy_requested {
+            if let Some(text) = self.copy_selection() {
+                ctx.output_mut(|o| o.copied_text = text);
+            }
+        }
+        if duplicate_requested {
+            self.duplicate_selection();
+            self.last_event = Some(Instant::now());
+        }
+
+        // deleting with the keyboard answers with the same responses as the close button of a
+        // node, so the document gets updated for them as well
+        let delete_released = ctx.input(|i| i.key_released(Key::Delete));
+        if delete_released && !ctx.wants_keyboard_input() {
+            let mut responses = self.delete_selection();
+            graph_response.node_responses.append(&mut responses);
+        }
+
+        if let Some(pending) = self.pending_conversion.clone() {
+            let conversion = pending.conversion.node();
+            let mut open = true;
+            let mut insert = false;
+            egui::Window::new("Incompatible connection")
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "A {:} value can not be used as {:}.",
+                        pending.output_type.name(),
+                        pending.input_type.name()
+                    ));
+                    if ui.button(format!("Insert {:}", conversion.label)).clicked() {
+                        insert = true;
+                    }
+                });
+            if insert {
+                self.graph.insert_conversion(&pending);
+                self.pending_conversion = None;
+                self.last_event = Some(Instant::now());
+            } else if !open {
+                self.pending_conversion = None;
+            }
+        }
+
+        if collapse_requested {
+            let selection = self.graph.state.selected_nodes.clone();
+            if let Some(node_id) = self.graph.collapse_nodes(&selection) {
+                self.graph.state.selected_nodes = vec![node_id];
+            }
+        }
+        if let Some(depth) = close_tabs_to {
+            while self.graph.open_subgraphs.len() > depth {
+                self.graph.close_subgraph();
+            }
+            self.last_event = Some(Instant::now());
+        }
+
+        for response in graph_response.node_responses.iter() {
+            match response {
+                NodeResponse::ConnectEventEnded { output, input } => {
+                    let graph = &self.graph.state.graph;
+                    let output_type = graph.outputs[*output].typ.clone();
+                    let input_type = graph.inputs[*input].typ.clone();
+                    // refused connections were not made, the input keeps its connection
+                    match WzrdConnectionCheck::new(graph, *output, *input) {
+                        WzrdConnectionCheck::Valid => {}
+                        WzrdConnectionCheck::Convertible(conversion) => {
+                            self.pending_conversion = Some(WzrdPendingConversion {
+                                output: *output,
+                                input: *input,
+                                output_type,
+                                input_type,
+                                conversion,
+                            });
+                        }
+                        WzrdConnectionCheck::Cycle => {
+                            warn!("Refusing connection, it would create a cycle in the graph");
+                        }
+                        WzrdConnectionCheck::Invalid => {
+                            warn!(
+                                "Refusing connection, {:} does not accept {:}",
+                                input_type.name(),
+                                output_type.name()
+                            );
+                        }
+                    }
+                }
+                NodeResponse::CreatedNode(node_id) => {
+                    self.graph.place_in_sequence(&[*node_id], None);
+                }
+                NodeResponse::DeleteNodeFull { node_id, .. } => {
+                    self.graph.pinned_nodes.remove(node_id);
+                    self.graph.unplaced_nodes.remove(node_id);
+                }
+                NodeResponse::MoveNode { node, .. } => {
+                    self.layout_animation = None;
+                    // the selection is dragged along with the node
+                    let graph = &mut self.graph;
+                    graph.pinned_nodes.insert(*node);
+                    graph
+                        .pinned_nodes
+                        .extend(graph.state.selected_nodes.iter().copied());
+                }
+                NodeResponse::User(WzrdResponse::OpenDefinition(node_id)) => {
+                    self.go_to_definition(*node_id);
+                }
+                NodeResponse::User(WzrdResponse::OpenSubgraph(node_id)) => {
+                    if let Err(err) = self.graph.open_subgraph(*node_id) {
+                        warn!("could not open the subgraph: {err}");
+                    }
+                }
+                NodeResponse::User(WzrdResponse::ExpandSubgraph(node_id)) => {
+                    match self.graph.expand_subgraph(*node_id) {
+                        Ok(inserted) => self.graph.state.selected_nodes = inserted,
+                        Err(err) => warn!("could not expand the subgraph: {err}"),
+                    }
+                }
+                _ => {}
+            }
+        }
 
         ctx.input(|i| {
-            if i.key_released(Key::I) {
+            if i.key_released(Key::I) && !in_subgraph {
                 let mut cache: NodeCache = HashMap::new();
-                let graph = self.evaluate_graph(&mut cache);
-                info!("{graph}");
+                match self.graph.evaluate_graph(&mut cache) {
+                    Ok(graph) => info!("{graph}"),
+                    Err(err) => warn!("{err}"),
+                }
             }
         });
 
-        if *self.format_requested.lock().unwrap() {
-            *self.format_requested.lock().unwrap() = false;
+        if *self.graph.format_requested.lock().unwrap() {
+            *self.graph.format_requested.lock().unwrap() = false;
+            format_requested = true;
+        }
+        if format_requested {
             self.format_graph();
+            ctx.request_repaint();
         }
 
+        let graph_changed = self.last_event != last_event
+            || collapse_requested
+            || graph_response.node_responses.iter().any(changes_graph);
+
         #[cfg(target_arch = "wasm32")]
         {
-            fn call_external_update(graph: &mut WzrdNodeGraph) {
-                let mut cache: NodeCache = HashMap::new();
-                let graph = graph.evaluate_graph(&mut cache);
-                update_document(&graph);
-            }
+            use std::cmp::Ordering;
 
-            if graph_response.node_responses.len() > 0 || self.last_event.is_none() {
+            if in_subgraph {
+                // the document is updated once the tab is closed again
+            } else if graph_response.node_responses.len() > 0 || self.last_event.is_none() {
                 self.last_event = Some(Instant::now());
             }
 
@@ -269,9 +930,9 @@ impl eframe::App for WzrdNodeGraph {
                             graph_response.node_responses.len(),
                             duration_since_last_update_ms
                         );
+                        // a graph that can't be generated is not tried again until the next edit
                         self.last_update = Some(Instant::now());
-
-                        call_external_update(self);
+                        self.update_document_now();
                     }
                     _ => {}
                 };
@@ -280,5 +941,52 @@ impl eframe::App for WzrdNodeGraph {
                 // call_external_update(self);
             }
         }
+
+        // refactorings rewrite the document in one go, so they run after the regular update
+        let selection = self.graph.state.selected_nodes.clone();
+        let refactored = if extract_requested {
+            self.graph.extract_method(&selection).map(|call_id| {
+                info!("extracted {:}", self.graph.state.graph[call_id].label);
+                vec![call_id]
+            })
+        } else if inline_requested {
+            match selection.first() {
+                Some(node_id) => self.animated(|graph| graph.inline_method(*node_id)),
+                None => Ok(vec![]),
+            }
+        } else {
+            Ok(vec![])
+        };
+        match refactored {
+            Ok(selection) if extract_requested || inline_requested => {
+                self.graph.state.selected_nodes = selection;
+                self.update_document_now();
+            }
+            Ok(_) => {}
+            Err(err) => warn!("{err}"),
+        }
+        if graph_changed || extract_requested || inline_requested {
+            self.types_outdated = true;
+            ctx.request_repaint();
+        }
+        let edited = graph_response.node_responses.iter().any(|response| {
+            matches!(
+                response,
+                NodeResponse::MoveNode { .. } | NodeResponse::User(WzrdResponse::ValueChanged(_))
+            )
+        });
+        let changed =
+            graph_changed || edited || format_requested || extract_requested || inline_requested;
+        if changed && !restored {
+            self.history.mark_changed();
+        }
+
+        // an edit is recorded once it is complete, not for every frame of a drag or keystroke
+        let editing = ctx.input(|i| i.pointer.any_down())
+            || ctx.wants_keyboard_input()
+            || self.layout_animation.is_some();
+        if !in_subgraph && !editing {
+            self.commit_history();
+        }
     }
 }
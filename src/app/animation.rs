@@ -0,0 +1,129 @@
+use crate::app::WzrdApp;
+use crate::graph_core::wzrd_node_graph::WzrdNodeGraph;
+use eframe::egui::Pos2;
+use egui_node_graph::NodeId;
+use instant::Instant;
+use slotmap::SecondaryMap;
+
+/// Time the nodes take to move into a new layout.
+const ANIMATION_MS: f32 = 300.0;
+
+/// A layout the nodes are moving into. The editor state holds the positions of the current
+/// frame, `to` the final ones.
+pub struct WzrdLayoutAnimation {
+    from: SecondaryMap<NodeId, Pos2>,
+    to: SecondaryMap<NodeId, Pos2>,
+    started: Instant,
+}
+
+impl WzrdApp {
+    /// Runs `edit` on the graph. Nodes it gives a new position are moved there over the next
+    /// frames instead of jumping, see [`WzrdApp::animate_layout`].
+    pub(super) fn animated<T>(&mut self, edit: impl FnOnce(&mut WzrdNodeGraph) -> T) -> T {
+        let from = self.graph.state.node_positions.clone();
+        let result = edit(&mut self.graph);
+        self.layout_animation = Some(WzrdLayoutAnimation {
+            from,
+            to: self.graph.state.node_positions.clone(),
+            started: Instant::now(),
+        });
+        result
+    }
+
+    /// Puts the nodes where a running layout animation has them in this frame, easing out
+    /// towards the final positions. Returns whether the animation needs another frame.
+    pub(super) fn animate_layout(&mut self) -> bool {
+        let animation = match &self.layout_animation {
+            Some(animation) => animation,
+            None => return false,
+        };
+        let progress = f32::min(
+            1.0,
+            animation.started.elapsed().as_secs_f32() * 1000.0 / ANIMATION_MS,
+        );
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        let state = &mut self.graph.state;
+        for (node_id, to) in animation.to.iter() {
+            // nodes may have been deleted while moving
+            if !state.graph.nodes.contains_key(node_id) {
+                continue;
+            }
+            let from = animation.from.get(node_id).copied().unwrap_or(*to);
+            state
+                .node_positions
+                .insert(node_id, from + (*to - from) * eased);
+        }
+        if progress < 1.0 {
+            true
+        } else {
+            self.layout_animation = None;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// An app whose nodes all sit at the origin, as if they had never been laid out.
+    fn app(code: &str) -> WzrdApp {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(code, None);
+        for position in graph.state.node_positions.values_mut() {
+            *position = Pos2::ZERO;
+        }
+        WzrdApp::new(graph)
+    }
+
+    /// Moves the start of the animation ahead, the next frame is its first one however long the
+    /// test took to get there.
+    fn hold(app: &mut WzrdApp) {
+        if let Some(animation) = &mut app.layout_animation {
+            animation.started += Duration::from_millis(ANIMATION_MS as u64);
+        }
+    }
+
+    fn finish(app: &mut WzrdApp) {
+        if let Some(animation) = &mut app.layout_animation {
+            animation.started -= Duration::from_millis(ANIMATION_MS as u64);
+        }
+    }
+
+    #[test]
+    fn nodes_start_moving_from_where_they_were() {
+        let mut app = app("puts 1.abs + 2\n");
+        app.animated(|graph| graph.format_graph());
+
+        hold(&mut app);
+        assert!(app.animate_layout());
+        for position in app.graph.state.node_positions.values() {
+            assert_eq!(*position, Pos2::ZERO);
+        }
+    }
+
+    #[test]
+    fn nodes_end_in_the_new_layout() {
+        let mut app = app("puts 1.abs + 2\n");
+        app.animated(|graph| graph.format_graph());
+        let layout = app.graph.state.node_positions.clone();
+
+        finish(&mut app);
+        assert!(!app.animate_layout());
+        assert!(app.layout_animation.is_none());
+        assert_eq!(app.graph.state.node_positions, layout);
+    }
+
+    #[test]
+    fn nodes_deleted_while_moving_are_left_out() {
+        let mut app = app("puts 1.abs + 2\n");
+        app.animated(|graph| graph.format_graph());
+        let deleted = app.graph.find_root_nodes()[0];
+        app.graph.delete_nodes(&[deleted]);
+
+        finish(&mut app);
+        app.animate_layout();
+        assert!(!app.graph.state.node_positions.contains_key(deleted));
+    }
+}
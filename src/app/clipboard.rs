@@ -0,0 +1,193 @@
+use crate::app::WzrdApp;
+use crate::graph_core::node::structs::{WzrdNodeData, WzrdResponse};
+use crate::graph_core::wzrd_node_graph::fragment::WzrdGraphFragment;
+use eframe::egui::{vec2, Pos2, Vec2};
+use egui_node_graph::NodeResponse;
+use log::warn;
+
+/// Offset of a duplicate from the nodes it was made of.
+const DUPLICATE_OFFSET: Vec2 = vec2(30.0, 30.0);
+
+#[cfg(feature = "persistence")]
+impl WzrdApp {
+    /// The selected nodes and the connections between them, serialized for the clipboard.
+    pub fn copy_selection(&self) -> Option<String> {
+        let state = &self.graph.state;
+        if state.selected_nodes.is_empty() {
+            return None;
+        }
+        let fragment =
+            WzrdGraphFragment::extract(&state.graph, &state.node_positions, &state.selected_nodes);
+        serde_json::to_string(&fragment)
+            .map_err(|err| warn!("could not copy the selection: {err}"))
+            .ok()
+    }
+
+    /// Copies the selection and deletes it. Nothing is deleted if it could not be copied.
+    pub fn cut_selection(
+        &mut self,
+    ) -> Option<(String, Vec<NodeResponse<WzrdResponse, WzrdNodeData>>)> {
+        let text = self.copy_selection()?;
+        Some((text, self.delete_selection()))
+    }
+
+    /// Pastes nodes copied from the editor, or parses the text as Ruby source otherwise. The new
+    /// nodes are centered on `position` and become the selection.
+    pub fn paste(&mut self, text: &str, position: Pos2) {
+        let inserted = match serde_json::from_str::<WzrdGraphFragment>(text) {
+            Ok(fragment) => {
+                let offset = position - fragment.center();
+                match self.graph.insert_fragment(&fragment, offset) {
+                    Ok(inserted) => inserted,
+                    Err(err) => {
+                        warn!("could not paste the nodes: {err}");
+                        return;
+                    }
+                }
+            }
+            Err(_) => self.graph.insert_code(text, position),
+        };
+        if !inserted.is_empty() {
+            self.graph.state.selected_nodes = inserted;
+        }
+    }
+}
+
+impl WzrdApp {
+    pub fn duplicate_selection(&mut self) {
+        let state = &self.graph.state;
+        let fragment =
+            WzrdGraphFragment::extract(&state.graph, &state.node_positions, &state.selected_nodes);
+        match self.graph.insert_fragment(&fragment, DUPLICATE_OFFSET) {
+            Ok(inserted) if !inserted.is_empty() => self.graph.state.selected_nodes = inserted,
+            Ok(_) => {}
+            Err(err) => warn!("could not duplicate the selection: {err}"),
+        }
+    }
+}
+
+/// Copying serializes the nodes, which needs the `persistence` feature. Without it nothing is
+/// copied and only Ruby code can be pasted.
+#[cfg(not(feature = "persistence"))]
+impl WzrdApp {
+    pub fn copy_selection(&self) -> Option<String> {
+        None
+    }
+
+    pub fn cut_selection(
+        &mut self,
+    ) -> Option<(String, Vec<NodeResponse<WzrdResponse, WzrdNodeData>>)> {
+        None
+    }
+
+    pub fn paste(&mut self, text: &str, position: Pos2) {
+        let inserted = self.graph.insert_code(text, position);
+        if !inserted.is_empty() {
+            self.graph.state.selected_nodes = inserted;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use super::*;
+    use crate::graph_core::wzrd_node_graph::WzrdNodeGraph;
+    use egui_node_graph::NodeId;
+
+    fn app(code: &str) -> WzrdApp {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(code, None);
+        graph.format_graph();
+        WzrdApp::new(graph)
+    }
+
+    fn select(app: &mut WzrdApp, labels: &[&str]) {
+        let graph = &app.graph.state.graph;
+        app.graph.state.selected_nodes = graph
+            .iter_nodes()
+            .filter(|node_id| labels.contains(&graph[*node_id].label.as_str()))
+            .collect();
+    }
+
+    fn connection_count(app: &WzrdApp) -> usize {
+        app.graph.state.graph.connections.len()
+    }
+
+    #[test]
+    fn nothing_is_copied_without_a_selection() {
+        let app = app("puts 1.abs\n");
+        assert!(app.copy_selection().is_none());
+    }
+
+    #[test]
+    fn pasted_nodes_keep_their_connections_and_become_the_selection() {
+        let mut app = app("puts 1.abs\n");
+        select(&mut app, &["puts", "abs"]);
+        let text = app.copy_selection().unwrap();
+
+        app.paste(&text, Pos2::new(500.0, 500.0));
+
+        assert_eq!(app.graph.state.graph.nodes.len(), 4);
+        assert_eq!(connection_count(&app), 2);
+        let selection = &app.graph.state.selected_nodes;
+        assert_eq!(selection.len(), 2);
+        let positions: Vec<Pos2> = selection
+            .iter()
+            .map(|node_id| app.graph.state.node_positions[*node_id])
+            .collect();
+        let center = positions
+            .iter()
+            .fold(Vec2::ZERO, |sum, position| sum + position.to_vec2())
+            / 2.0;
+        assert_eq!(center.to_pos2(), Pos2::new(500.0, 500.0));
+    }
+
+    #[test]
+    fn cut_deletes_what_it_copied() {
+        let mut app = app("puts 1.abs\nputs 2\n");
+        select(&mut app, &["abs"]);
+        let (text, _) = app.cut_selection().unwrap();
+
+        assert_eq!(app.graph.state.graph.nodes.len(), 2);
+        app.paste(&text, Pos2::ZERO);
+        assert_eq!(app.graph.state.graph.nodes.len(), 3);
+    }
+
+    #[test]
+    fn text_that_is_not_a_fragment_is_pasted_as_ruby() {
+        let mut app = app("puts 1\n");
+        app.paste("puts 2.abs", Pos2::ZERO);
+
+        assert_eq!(app.graph.state.graph.nodes.len(), 3);
+        assert_eq!(app.graph.state.selected_nodes.len(), 2);
+    }
+
+    #[test]
+    fn invalid_fragments_are_not_pasted() {
+        let mut app = app("puts 1.abs\n");
+        select(&mut app, &["puts", "abs"]);
+        let mut fragment: WzrdGraphFragment =
+            serde_json::from_str(&app.copy_selection().unwrap()).unwrap();
+        fragment.connections[0].output.0 = 9;
+        let selection: Vec<NodeId> = app.graph.state.selected_nodes.clone();
+
+        app.paste(&serde_json::to_string(&fragment).unwrap(), Pos2::ZERO);
+
+        assert_eq!(app.graph.state.graph.nodes.len(), 2);
+        assert_eq!(app.graph.state.selected_nodes, selection);
+    }
+
+    #[test]
+    fn duplicates_are_offset_from_the_original() {
+        let mut app = app("puts 1.abs\n");
+        select(&mut app, &["abs"]);
+        let original = app.graph.state.selected_nodes[0];
+
+        app.duplicate_selection();
+
+        let duplicate = app.graph.state.selected_nodes[0];
+        assert_ne!(duplicate, original);
+        let positions = &app.graph.state.node_positions;
+        assert_eq!(positions[duplicate] - positions[original], DUPLICATE_OFFSET);
+    }
+}
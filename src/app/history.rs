@@ -0,0 +1,256 @@
+use crate::app::WzrdApp;
+use crate::graph_core::node::structs::{WzrdFunction, WzrdGraphState, WzrdNodeTemplates};
+use crate::graph_core::wzrd_node_graph::{WzrdDocument, WzrdGraph};
+use eframe::egui::Pos2;
+use egui_node_graph::NodeId;
+use slotmap::SecondaryMap;
+use std::collections::{HashSet, LinkedList};
+
+/// Number of steps that can be undone.
+const HISTORY_LIMIT: usize = 100;
+
+/// Everything an edit of the graph can change, the view of the editor is left out. Extracting
+/// a method also registers it as a node, so the node templates are part of it as well. Editing
+/// the document in the text editor changes the code around the graph, so that is kept too.
+#[derive(Clone)]
+pub struct WzrdSnapshot {
+    graph: WzrdGraph,
+    node_positions: SecondaryMap<NodeId, Pos2>,
+    node_order: Vec<NodeId>,
+    pinned_nodes: HashSet<NodeId>,
+    unplaced_nodes: HashSet<NodeId>,
+    extracted_methods: Vec<String>,
+    user_state: WzrdGraphState,
+    node_templates: WzrdNodeTemplates,
+    document: Option<WzrdDocument>,
+    function_stack: LinkedList<WzrdFunction>,
+    unsupported_code: Vec<String>,
+}
+
+/// Snapshot based history. The state after the last recorded edit is kept, an edit is recorded
+/// once the responses of the editor said the graph changed and the edit is complete.
+#[derive(Default)]
+pub struct WzrdHistory {
+    undo: Vec<WzrdSnapshot>,
+    redo: Vec<WzrdSnapshot>,
+    committed: Option<WzrdSnapshot>,
+    changed: bool,
+}
+
+impl WzrdHistory {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || (self.changed && self.committed.is_some())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Notes that the graph changed since the last recorded step.
+    pub fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+}
+
+impl WzrdApp {
+    fn snapshot(&self) -> WzrdSnapshot {
+        let graph = &self.graph;
+        WzrdSnapshot {
+            graph: graph.state.graph.clone(),
+            node_positions: graph.state.node_positions.clone(),
+            node_order: graph.state.node_order.clone(),
+            pinned_nodes: graph.pinned_nodes.clone(),
+            unplaced_nodes: graph.unplaced_nodes.clone(),
+            extracted_methods: graph.extracted_methods.clone(),
+            user_state: graph.user_state.clone(),
+            node_templates: graph.node_templates.clone(),
+            document: graph.document.clone(),
+            function_stack: graph.function_stack.clone(),
+            unsupported_code: graph.unsupported_code.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: WzrdSnapshot) {
+        let graph = &mut self.graph;
+        graph.state.graph = snapshot.graph;
+        graph.state.node_positions = snapshot.node_positions;
+        graph.state.node_order = snapshot.node_order;
+        graph.pinned_nodes = snapshot.pinned_nodes;
+        graph.unplaced_nodes = snapshot.unplaced_nodes;
+        graph.extracted_methods = snapshot.extracted_methods;
+        graph.user_state = snapshot.user_state;
+        graph.node_templates = snapshot.node_templates;
+        graph.document = snapshot.document;
+        graph.function_stack = snapshot.function_stack;
+        graph.unsupported_code = snapshot.unsupported_code;
+        let nodes = &graph.state.graph.nodes;
+        graph
+            .state
+            .selected_nodes
+            .retain(|node_id| nodes.contains_key(*node_id));
+        self.pending_conversion = None;
+        self.layout_animation = None;
+        self.types_outdated = true;
+    }
+
+    /// Records the graph as a new step of the history if it was marked as changed since the
+    /// last one. Called once an edit is complete, so a drag or a typed constant ends up as a
+    /// single step. The first call records the state the history starts from.
+    pub fn commit_history(&mut self) {
+        if self.history.committed.is_some() && !self.history.changed {
+            return;
+        }
+        let snapshot = self.snapshot();
+        if let Some(previous) = self.history.committed.replace(snapshot) {
+            self.history.undo.push(previous);
+            if self.history.undo.len() > HISTORY_LIMIT {
+                self.history.undo.remove(0);
+            }
+            self.history.redo.clear();
+        }
+        self.history.changed = false;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.commit_history();
+        let previous = match self.history.undo.pop() {
+            Some(previous) => previous,
+            None => return false,
+        };
+        if let Some(current) = self.history.committed.replace(previous.clone()) {
+            self.history.redo.push(current);
+        }
+        self.restore(previous);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.commit_history();
+        let next = match self.history.redo.pop() {
+            Some(next) => next,
+            None => return false,
+        };
+        if let Some(current) = self.history.committed.replace(next.clone()) {
+            self.history.undo.push(current);
+        }
+        self.restore(next);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_core::wzrd_node_graph::WzrdNodeGraph;
+    use std::collections::HashMap;
+
+    fn app(code: &str) -> WzrdApp {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(code, None);
+        let mut app = WzrdApp::new(graph);
+        app.commit_history();
+        app
+    }
+
+    fn nodes_labeled(app: &WzrdApp, label: &str) -> Vec<NodeId> {
+        let graph = &app.graph.state.graph;
+        graph
+            .iter_nodes()
+            .filter(|node_id| graph[*node_id].label == label)
+            .collect()
+    }
+
+    fn delete(app: &mut WzrdApp, label: &str) {
+        let node_ids = nodes_labeled(app, label);
+        app.graph.delete_nodes(&node_ids);
+        app.history.mark_changed();
+        app.commit_history();
+    }
+
+    #[test]
+    fn unchanged_graphs_are_not_recorded() {
+        let mut app = app("puts 1.abs\n");
+        app.commit_history();
+        assert!(!app.history.can_undo());
+        assert!(!app.undo());
+    }
+
+    #[test]
+    fn undo_and_redo_restore_recorded_edits() {
+        let mut app = app("puts 1.abs\n");
+        delete(&mut app, "abs");
+        assert!(nodes_labeled(&app, "abs").is_empty());
+
+        assert!(app.undo());
+        assert_eq!(nodes_labeled(&app, "abs").len(), 1);
+        assert!(!app.history.can_undo());
+
+        assert!(app.redo());
+        assert!(nodes_labeled(&app, "abs").is_empty());
+        assert!(!app.history.can_redo());
+    }
+
+    #[test]
+    fn a_new_edit_drops_the_steps_that_were_undone() {
+        let mut app = app("puts 1.abs\nputs 2\n");
+        delete(&mut app, "abs");
+        app.undo();
+        assert!(app.history.can_redo());
+
+        delete(&mut app, "puts");
+        assert!(!app.history.can_redo());
+    }
+
+    #[test]
+    fn edits_are_recorded_when_undone_before_they_were_committed() {
+        let mut app = app("puts 1.abs\n");
+        let node_ids = nodes_labeled(&app, "abs");
+        app.graph.delete_nodes(&node_ids);
+        app.history.mark_changed();
+
+        assert!(app.undo());
+        assert_eq!(nodes_labeled(&app, "abs").len(), 1);
+        assert!(app.redo());
+        assert!(nodes_labeled(&app, "abs").is_empty());
+    }
+
+    #[test]
+    fn undoing_an_extraction_forgets_the_extracted_method() {
+        let mut app = app("class Greeter\n  def greet(name)\n    puts name.upcase\n  end\nend\n");
+        let original = app.graph.evaluate_graph(&mut HashMap::new()).unwrap();
+        let selection = nodes_labeled(&app, "upcase");
+        app.graph.extract_method(&selection).unwrap();
+        app.history.mark_changed();
+        app.commit_history();
+        assert!(app.graph.node_templates.find("extracted_method").is_some());
+
+        assert!(app.undo());
+        let graph = &app.graph;
+        assert!(graph.extracted_methods.is_empty());
+        assert!(graph.node_templates.find("extracted_method").is_none());
+        assert!(!graph
+            .user_state
+            .project_methods
+            .contains_key("extracted_method"));
+        assert_eq!(nodes_labeled(&app, "upcase").len(), 1);
+        assert_eq!(
+            app.graph.evaluate_graph(&mut HashMap::new()).unwrap(),
+            original
+        );
+    }
+
+    #[test]
+    fn undoing_an_edit_of_the_document_restores_the_code_around_the_graph() {
+        let mut app = app("class A\n  def main\n    puts 1\n  end\nend\n");
+        let original = app.graph.evaluate_graph(&mut HashMap::new()).unwrap();
+        app.document_changed("module B\n  def run(a)\n    puts a\n  end\nend\n");
+        app.commit_history();
+
+        assert!(app.undo());
+        assert_eq!(app.graph.function_stack.back().unwrap().name, "main");
+        assert_eq!(
+            app.graph.evaluate_graph(&mut HashMap::new()).unwrap(),
+            original
+        );
+    }
+}
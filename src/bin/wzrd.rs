@@ -0,0 +1,11 @@
+//! Converts between Ruby and graphs on the command line, see [`graph_editor::cli`].
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(graph_editor::cli::run(&args));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,179 @@
+//! Commands to convert between Ruby and graphs without starting the editor.
+
+use crate::graph_core::wzrd_node_graph::{WzrdNodeGraph, WzrdSavedLayout, LAYOUT_COMMENT};
+use crate::graph_core::{check_round_trip, import_ruby, new_graph, RoundTrip};
+use anyhow::{anyhow, bail, Context};
+use std::path::Path;
+
+const USAGE: &str = "usage:
+    wzrd ruby2graph <file.rb> [-o <file.json>]   build the graph of a Ruby file
+    wzrd graph2ruby <file.json> [-o <file.rb>]   generate the Ruby code of a graph file
+    wzrd roundtrip [--check] <file.rb>...        regenerate Ruby files through their graph
+    wzrd layout <file.rb|file.json> [-o <file>]  lay out a graph from scratch";
+
+/// Runs the command in `args`, without the program name, and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => {
+            eprintln!("{USAGE}");
+            return 2;
+        }
+    };
+    let result = match command {
+        "ruby2graph" => convert(args, ruby_to_graph),
+        "graph2ruby" => convert(args, graph_to_ruby),
+        "layout" => convert(args, layout),
+        "roundtrip" => roundtrip(args),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(true)
+        }
+        _ => Err(anyhow!("unknown command {command}\n{USAGE}")),
+    };
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(err) => {
+            eprintln!("{err:#}");
+            2
+        }
+    }
+}
+
+/// Reads the file given in `args` and writes what `transform` makes of it to the file after
+/// `-o`, or to stdout.
+fn convert(
+    args: &[String],
+    transform: fn(&Path, &str) -> anyhow::Result<String>,
+) -> anyhow::Result<bool> {
+    let (input, output) = match args {
+        [input] => (input, None),
+        [input, flag, output] if flag == "-o" => (input, Some(output)),
+        _ => bail!("{USAGE}"),
+    };
+    let path = Path::new(input);
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("could not read {input}"))?;
+    let converted = transform(path, &contents).with_context(|| format!("in {input}"))?;
+    match output {
+        Some(output) => std::fs::write(output, converted)
+            .with_context(|| format!("could not write {output}"))?,
+        None => print!("{converted}"),
+    }
+    Ok(true)
+}
+
+fn ruby_to_graph(_path: &Path, code: &str) -> anyhow::Result<String> {
+    let graph = import_ruby(code);
+    // the graph file would not have it, converting it back would drop it
+    if let Some(unsupported) = graph.unsupported_code.first() {
+        bail!("the graph can't show {unsupported}");
+    }
+    graph.to_json()
+}
+
+fn graph_to_ruby(_path: &Path, json: &str) -> anyhow::Result<String> {
+    WzrdNodeGraph::from_json(json)?.evaluate_document()
+}
+
+/// Graph files get new positions, Ruby files a new layout comment with the code left as it is.
+fn layout(path: &Path, contents: &str) -> anyhow::Result<String> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        let mut editor = WzrdNodeGraph::from_json(contents)?;
+        editor.format_graph();
+        return editor.to_json();
+    }
+
+    let mut editor = new_graph();
+    editor.load_document(contents, None);
+    editor.format_graph();
+    let code: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(LAYOUT_COMMENT.trim_end()))
+        .collect();
+    let layout: WzrdSavedLayout = editor.saved_layout();
+    Ok(format!(
+        "{:}\n{:}\n",
+        code.join("\n").trim_end(),
+        layout.to_comment()
+    ))
+}
+
+/// Generates code from the graph of each file and reports what the round trip loses. With
+/// `--check` only the problems are printed.
+fn roundtrip(args: &[String]) -> anyhow::Result<bool> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if files.is_empty() {
+        bail!("{USAGE}");
+    }
+
+    let mut clean = true;
+    for file in files {
+        let code =
+            std::fs::read_to_string(file).with_context(|| format!("could not read {file}"))?;
+        let RoundTrip {
+            generated,
+            problems,
+        } = check_round_trip(&code);
+        if !check {
+            print!("{generated}");
+        }
+        for problem in problems.iter() {
+            eprintln!("{file}: {problem}");
+        }
+        clean &= problems.is_empty();
+    }
+    Ok(clean)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file in a directory of its own under the temporary directory.
+    fn temporary_file(test: &str, name: &str, contents: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("wzrd-cli-{test}"));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn run_with(args: &[&str]) -> i32 {
+        run(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn roundtrip_check_fails_only_when_code_is_lost() {
+        let clean = temporary_file("roundtrip", "clean.rb", "def main(a)\n  puts a\nend\n");
+        let lossy = temporary_file("roundtrip", "lossy.rb", "while x\n  x = x - 1\nend\n");
+        assert_eq!(run_with(&["roundtrip", "--check", &clean]), 0);
+        assert_eq!(run_with(&["roundtrip", "--check", &clean, &lossy]), 1);
+    }
+
+    #[test]
+    fn graph_files_convert_back_to_the_same_code() {
+        let ruby = temporary_file("convert", "main.rb", "def main(a)\n  puts a\nend\n");
+        let json = ruby.replace("main.rb", "main.json");
+        let regenerated = ruby.replace("main.rb", "regenerated.rb");
+        assert_eq!(run_with(&["ruby2graph", &ruby, "-o", &json]), 0);
+        assert_eq!(run_with(&["graph2ruby", &json, "-o", &regenerated]), 0);
+
+        let code = std::fs::read_to_string(regenerated).unwrap();
+        assert!(code.starts_with("def main(a)\n    puts a\nend\n"));
+        assert!(code.contains(LAYOUT_COMMENT));
+    }
+
+    #[test]
+    fn usage_errors_exit_with_2() {
+        assert_eq!(run_with(&[]), 2);
+        assert_eq!(run_with(&["convert"]), 2);
+        assert_eq!(run_with(&["ruby2graph"]), 2);
+        assert_eq!(run_with(&["graph2ruby", "missing.json"]), 2);
+    }
+}
@@ -0,0 +1,158 @@
+//! The graph model without the editor: importing Ruby, generating code, checking and laying
+//! out graphs. Nothing here needs a window, the egui editor in [`crate::app`] is one front end
+//! on top of it and the `wzrd` command line another.
+
+pub mod node;
+pub mod wzrd_node_graph;
+
+use crate::graph_core::node::custom::CUSTOM_NODES_FILE;
+use crate::graph_core::wzrd_node_graph::{NodeCache, WzrdNodeGraph};
+use lib_ruby_parser::{Parser, ParserOptions, ParserResult};
+use std::collections::HashMap;
+
+/// Tokens naming something the code refers to. Code dropped by the parser or the generator
+/// takes some of them along, see [`dropped_names`].
+const NAME_TOKENS: &[&str] = &[
+    "tIDENTIFIER",
+    "tFID",
+    "tCONSTANT",
+    "tIVAR",
+    "tGVAR",
+    "tCVAR",
+    "kNIL",
+    "kTRUE",
+    "kFALSE",
+];
+
+/// A graph with the standard nodes and the custom nodes of the working directory, as the
+/// editor starts with.
+pub fn new_graph() -> WzrdNodeGraph {
+    let mut graph = WzrdNodeGraph::new();
+    if let Ok(custom_nodes) = std::fs::read_to_string(CUSTOM_NODES_FILE) {
+        graph.load_custom_nodes(&custom_nodes);
+    }
+    graph
+}
+
+/// The graph of `code`, laid out unless the code carries a layout.
+pub fn import_ruby(code: &str) -> WzrdNodeGraph {
+    let mut graph = new_graph();
+    graph.load_document(code, None);
+    if *graph.format_requested.lock().unwrap() {
+        graph.format_graph();
+    }
+    graph
+}
+
+/// The Ruby code of a graph, without the layout comment.
+pub fn generate_ruby(graph: &mut WzrdNodeGraph) -> anyhow::Result<String> {
+    graph.evaluate_graph(&mut NodeCache::new())
+}
+
+/// Code generated from the graph of some Ruby code, with what got lost on the way.
+pub struct RoundTrip {
+    pub generated: String,
+    pub problems: Vec<String>,
+}
+
+/// Generates code from the graph of `code`. The generated code has to build the same graph
+/// again, generate the same code again, and keep every name `code` refers to.
+pub fn check_round_trip(code: &str) -> RoundTrip {
+    let mut problems = vec![];
+    let mut first = import_ruby(code);
+    let once = match generate_ruby(&mut first) {
+        Ok(once) => once,
+        Err(err) => {
+            return RoundTrip {
+                generated: String::new(),
+                problems: vec![format!("the graph of the code can't be generated: {err}")],
+            }
+        }
+    };
+    let mut second = import_ruby(&once);
+    let twice = generate_ruby(&mut second).unwrap_or_else(|err| {
+        problems.push(format!(
+            "the graph of the generated code can't be generated: {err}"
+        ));
+        String::new()
+    });
+
+    let (first_outline, second_outline) = (first.outline(), second.outline());
+    if first_outline != second_outline {
+        problems.push(format!(
+            "the generated code builds a different graph\n--- parsed\n{first_outline}\n--- regenerated\n{second_outline}"
+        ));
+    }
+    if once != twice {
+        problems.push(format!(
+            "generating the code again changes it\n--- once\n{once}\n--- twice\n{twice}"
+        ));
+    }
+    let dropped = dropped_names(code, &once);
+    if !dropped.is_empty() {
+        problems.push(format!("the generated code drops {:}", dropped.join(", ")));
+    }
+    RoundTrip {
+        generated: once,
+        problems,
+    }
+}
+
+fn names(code: &str) -> HashMap<String, usize> {
+    let options = ParserOptions {
+        record_tokens: true,
+        ..Default::default()
+    };
+    let ParserResult { tokens, .. } = Parser::new(code, options).do_parse();
+    let mut names: HashMap<String, usize> = HashMap::new();
+    for token in tokens
+        .iter()
+        .filter(|token| NAME_TOKENS.contains(&token.token_name()))
+    {
+        *names
+            .entry(token.token_value.to_string_lossy())
+            .or_default() += 1;
+    }
+    names
+}
+
+/// Names used in `original` more often than in `generated`. Literals are left out, the
+/// generator is free to write them differently.
+fn dropped_names(original: &str, generated: &str) -> Vec<String> {
+    let generated = names(generated);
+    let mut dropped: Vec<String> = names(original)
+        .into_iter()
+        .filter(|(name, count)| generated.get(name).copied().unwrap_or(0) < *count)
+        .map(|(name, _)| name)
+        .collect();
+    dropped.sort();
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_the_graph_supports_round_trips_cleanly() {
+        let round_trip = check_round_trip("def main(a)\n  b = a + 1\n  puts b\nend\n");
+        assert!(round_trip.problems.is_empty(), "{:?}", round_trip.problems);
+        assert_eq!(
+            round_trip.generated,
+            "def main(a)\n    b = (a+1)\n    puts b\nend\n"
+        );
+    }
+
+    #[test]
+    fn code_the_graph_can_not_show_is_reported() {
+        let round_trip = check_round_trip("x = 1\nwhile x < 3\n  x = x + 1\nend\nputs x\n");
+        assert!(round_trip.generated.is_empty());
+        assert!(round_trip.problems[0].contains("while x < 3"));
+    }
+
+    #[test]
+    fn literals_written_differently_are_not_dropped() {
+        assert!(dropped_names("puts 0x10, :a", "puts 16, :a").is_empty());
+        assert_eq!(dropped_names("puts a, b", "puts a"), vec!["b"]);
+    }
+}
@@ -0,0 +1,808 @@
+pub mod custom;
+pub mod structs;
+
+use egui_node_graph::{Graph, InputParamKind, NodeId, NodeTemplateIter, NodeTemplateTrait};
+use enum_iterator::{all, Sequence};
+use lazy_static::lazy_static;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use structs::*;
+
+impl Default for WzrdValueType {
+    fn default() -> Self {
+        Self::Float { value: 0.0 }
+    }
+}
+
+impl NodeTemplateTrait for WzrdNode {
+    type NodeData = WzrdNodeData;
+    type DataType = WzrdNodeDataType;
+    type ValueType = WzrdValueType;
+    type UserState = WzrdGraphState;
+    type CategoryType = String;
+
+    fn node_finder_label(&self, _user_state: &mut Self::UserState) -> Cow<'_, str> {
+        Cow::Borrowed(&self.label)
+    }
+
+    fn node_finder_categories(&self, _user_state: &mut Self::UserState) -> Vec<String> {
+        self.category.iter().cloned().collect()
+    }
+
+    fn node_graph_label(&self, user_state: &mut Self::UserState) -> String {
+        self.node_finder_label(user_state).into()
+    }
+
+    fn user_data(&self, _user_state: &mut Self::UserState) -> Self::NodeData {
+        WzrdNodeData {
+            template: self.clone(),
+            subgraph: None,
+            // numbered once the node is part of a graph, see `WzrdNodeGraph::place_in_sequence`
+            sequence: 0,
+        }
+    }
+
+    fn build_node(
+        &self,
+        graph: &mut Graph<Self::NodeData, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+        node_id: NodeId,
+    ) {
+        for input in self.inputs.clone() {
+            // only sockets with a value type have a widget to edit an inline constant
+            let kind = match input.data_type {
+                WzrdValueType::Any => InputParamKind::ConnectionOnly,
+                _ => InputParamKind::ConnectionOrConstant,
+            };
+            graph.add_input_param(
+                node_id,
+                input.name,
+                input.data_type.clone().into(),
+                input.data_type,
+                kind,
+                true,
+            );
+        }
+
+        for output in self.outputs.clone() {
+            graph.add_output_param(node_id, output.name, output.data_type.into());
+        }
+    }
+}
+
+/// How a call of the method behind a node is written in Ruby.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WzrdCallStyle {
+    /// `value.method(arguments)` or an operator, the receiver is the first input.
+    Receiver,
+    /// `method(arguments)` without a receiver, like `puts` or a project method.
+    Kernel,
+}
+
+/// Labels of the nodes that stand for Ruby syntax rather than a call of their label.
+const SYNTAX_LABELS: [&str; 7] = [
+    "Constant",
+    "Variable",
+    "If",
+    "Assignment",
+    "output",
+    "Block",
+    "Subgraph",
+];
+
+impl WzrdNode {
+    /// How calls are written that this node can be parsed from, judged by its template.
+    pub fn call_style(&self) -> Option<WzrdCallStyle> {
+        if SYNTAX_LABELS.contains(&self.label.as_str()) {
+            return None;
+        }
+        let template = self.template.as_deref()?;
+        match template.strip_prefix(self.label.as_str()) {
+            Some(rest) if rest.is_empty() || rest.starts_with([' ', '(']) => {
+                Some(WzrdCallStyle::Kernel)
+            }
+            // prefix operators like `!$0`
+            Some(rest) if rest.starts_with("$0") => Some(WzrdCallStyle::Receiver),
+            _ if template.starts_with("$0.") || template.starts_with("($0") => {
+                Some(WzrdCallStyle::Receiver)
+            }
+            _ => None,
+        }
+    }
+
+    /// A node for a call the library has no node for, so the call is kept as it was written.
+    /// `inputs` includes the receiver.
+    pub fn generic_call(name: &str, style: WzrdCallStyle, inputs: usize) -> WzrdNode {
+        let placeholders = |from: usize| {
+            (from..inputs)
+                .map(|index| format!("${index}"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let is_operator = name.chars().all(|char| "+-*/%<=>!&|^~".contains(char));
+        let (template, first_argument) = match style {
+            WzrdCallStyle::Receiver if is_operator && inputs == 2 => (format!("($0{name}$1)"), 1),
+            WzrdCallStyle::Receiver if name == "[]" => (format!("$0[{}]", placeholders(1)), 1),
+            WzrdCallStyle::Receiver if inputs > 1 => (format!("$0.{name}({})", placeholders(1)), 1),
+            WzrdCallStyle::Receiver => (format!("$0.{name}"), 1),
+            WzrdCallStyle::Kernel if inputs > 0 => (format!("{name}({})", placeholders(0)), 0),
+            WzrdCallStyle::Kernel => (name.to_string(), 0),
+        };
+        // a call must not be taken for the syntax node of the same label
+        let label = if SYNTAX_LABELS.contains(&name) {
+            format!("{name}()")
+        } else {
+            name.into()
+        };
+        WzrdNode {
+            template: Some(template),
+            label,
+            category: None,
+            color: None,
+            inputs: (0..inputs)
+                .map(|index| {
+                    let name = if index < first_argument {
+                        "receiver".to_string()
+                    } else {
+                        format!("argument {:}", index - first_argument + 1)
+                    };
+                    param(&name, WzrdValueType::Any, index as u8 + 1)
+                })
+                .collect(),
+            outputs: vec![param("out", WzrdValueType::Any, 1)],
+        }
+    }
+
+    /// A node for a range whose bounds are not integer literals, a missing bound leaves the
+    /// range beginless or endless.
+    pub fn range(exclusive: bool, start: bool, end: bool) -> WzrdNode {
+        let operator = if exclusive { "..." } else { ".." };
+        let mut inputs = vec![];
+        if start {
+            inputs.push(param("start", WzrdValueType::Any, 1));
+        }
+        if end {
+            inputs.push(param("end", WzrdValueType::Any, inputs.len() as u8 + 1));
+        }
+        let template = match (start, end) {
+            (true, true) => format!("($0{operator}$1)"),
+            (true, false) => format!("($0{operator})"),
+            (false, true) => format!("({operator}$0)"),
+            (false, false) => format!("(nil{operator})"),
+        };
+        WzrdNode {
+            template: Some(template),
+            label: operator.into(),
+            category: None,
+            color: None,
+            inputs,
+            outputs: vec![param("out", WzrdValueType::Any, 1)],
+        }
+    }
+}
+
+impl WzrdNodeTemplates {
+    pub fn create_node(
+        &mut self,
+        label: &str,
+        template: Option<String>,
+        category: Option<String>,
+        color: Option<[u8; 3]>,
+        inputs: Vec<WzrdType>,
+        outputs: Vec<WzrdType>,
+    ) -> &WzrdNode {
+        let new_node = WzrdNode {
+            template,
+            label: label.into(),
+            category,
+            color,
+            inputs,
+            outputs,
+        };
+        self.0.push(new_node);
+        &self.0[self.0.len() - 1]
+    }
+
+    pub fn remove(&mut self, label: &str) {
+        self.0.retain(|node| node.label != label);
+    }
+}
+
+impl NodeTemplateIter for WzrdNodeTemplates {
+    type Item = WzrdNode;
+
+    fn all_kinds(&self) -> Vec<Self::Item> {
+        self.0.clone()
+    }
+}
+
+#[derive(Eq, Hash, PartialEq, Sequence, Clone)]
+pub enum WzrdNodes {
+    Constant,
+    Add,
+    Multiply,
+    Output,
+    Variable,
+    If,
+    Assignment,
+    Puts,
+    Block,
+    // Math
+    Subtract,
+    Divide,
+    Modulo,
+    Power,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    // Comparison
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessOrEqual,
+    GreaterOrEqual,
+    // Logic
+    And,
+    Or,
+    Not,
+    IsNil,
+    // String
+    Upcase,
+    Downcase,
+    Capitalize,
+    Strip,
+    Length,
+    StartWith,
+    EndWith,
+    Gsub,
+    Split,
+    // Collections
+    Size,
+    IsEmpty,
+    Include,
+    Join,
+    First,
+    Last,
+    Max,
+    Min,
+    Sum,
+    Sort,
+    Uniq,
+    Reverse,
+    // Conversion
+    ToS,
+    ToI,
+    ToF,
+    ToA,
+    ToSym,
+    // Kernel
+    P,
+    Print,
+    Raise,
+}
+
+pub const MATH: &str = "Math";
+pub const COMPARISON: &str = "Comparison";
+pub const LOGIC: &str = "Logic";
+pub const STRING: &str = "String";
+pub const COLLECTIONS: &str = "Collections";
+pub const CONVERSION: &str = "Conversion";
+pub const KERNEL: &str = "Kernel";
+
+fn param(name: &str, data_type: WzrdValueType, order: u8) -> WzrdType {
+    WzrdType {
+        name: name.into(),
+        data_type,
+        order,
+    }
+}
+
+fn number() -> WzrdValueType {
+    WzrdValueType::Number {
+        value: 0.0,
+        decimal: false,
+    }
+}
+
+fn string() -> WzrdValueType {
+    WzrdValueType::String { value: "".into() }
+}
+
+/// A node calling a ruby method, `inputs` are numbered in the order of the `$N` placeholders.
+fn method_node(
+    category: &str,
+    label: &str,
+    template: &str,
+    inputs: Vec<(&str, WzrdValueType)>,
+    output: Option<WzrdValueType>,
+) -> WzrdNode {
+    WzrdNode {
+        template: Some(template.into()),
+        label: label.into(),
+        category: Some(category.into()),
+        color: None,
+        inputs: inputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, data_type))| param(name, data_type, i as u8 + 1))
+            .collect(),
+        outputs: output
+            .map(|data_type| vec![param("out", data_type, 1)])
+            .unwrap_or_default(),
+    }
+}
+
+fn binary_operator(category: &str, operator: &str, output: WzrdValueType) -> WzrdNode {
+    let input_type = if category == MATH {
+        number()
+    } else {
+        WzrdValueType::Any
+    };
+    method_node(
+        category,
+        operator,
+        &format!("($0{operator}$1)"),
+        vec![("value1", input_type.clone()), ("value2", input_type)],
+        Some(output),
+    )
+}
+
+lazy_static! {
+    static ref NODE_MAP: HashMap<WzrdNodes, WzrdNode> = all::<WzrdNodes>()
+        .map(|node_enum| (node_enum.clone(), node_enum.build()))
+        .collect();
+    static ref NODE_LABEL_MAP: HashMap<String, WzrdNode> = all::<WzrdNodes>()
+        .map(|node_enum| node_enum.build())
+        .map(|node| (node.label.clone(), node))
+        .collect();
+}
+
+impl WzrdNodes {
+    pub fn node(&self) -> WzrdNode {
+        NODE_MAP[self].clone()
+    }
+
+    pub fn find_node(label: &str) -> Option<WzrdNode> {
+        NODE_LABEL_MAP.get(label).cloned()
+    }
+
+    fn build(&self) -> WzrdNode {
+        match self {
+            WzrdNodes::Constant => WzrdNode {
+                template: None,
+                label: "Constant".into(),
+                category: None,
+                color: None,
+                inputs: vec![WzrdType {
+                    name: "value".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+                outputs: vec![WzrdType {
+                    name: "out".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::Variable => WzrdNode {
+                template: None,
+                label: "Variable".into(),
+                category: None,
+                color: None,
+                inputs: vec![],
+                outputs: vec![],
+            },
+            WzrdNodes::Add => WzrdNode {
+                template: Some("($0+$1)".into()),
+                label: "+".to_string(),
+                category: Some(MATH.into()),
+                color: None,
+                // `+` also joins strings and `*` repeats them, the signature rules of the
+                // operators work out the type of the result
+                inputs: vec![
+                    WzrdType {
+                        name: "value1".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 1,
+                    },
+                    WzrdType {
+                        name: "value2".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 2,
+                    },
+                ],
+                outputs: vec![WzrdType {
+                    name: "out".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::Multiply => WzrdNode {
+                template: Some("($0*$1)".into()),
+                label: "*".to_string(),
+                category: Some(MATH.into()),
+                color: None,
+                inputs: vec![
+                    WzrdType {
+                        name: "value1".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 1,
+                    },
+                    WzrdType {
+                        name: "value2".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 2,
+                    },
+                ],
+                outputs: vec![WzrdType {
+                    name: "out".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::Output => WzrdNode {
+                template: Some("return $0".into()),
+                label: "output".into(),
+                category: Some(KERNEL.into()),
+                color: None,
+                inputs: vec![WzrdType {
+                    name: "output".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+                // only connected when returning early from a branch of an If
+                outputs: vec![WzrdType {
+                    name: "return".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::If => WzrdNode {
+                label: "If".into(),
+                template: Some("($0) ? $1 : $2".into()),
+                category: Some(LOGIC.into()),
+                color: None,
+                inputs: vec![
+                    WzrdType {
+                        name: "condition".into(),
+                        data_type: WzrdValueType::Expression { value: "".into() },
+                        order: 1,
+                    },
+                    WzrdType {
+                        name: "then".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 2,
+                    },
+                    WzrdType {
+                        name: "else".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 3,
+                    },
+                ],
+                outputs: vec![WzrdType {
+                    name: "".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::Assignment => WzrdNode {
+                label: "Assignment".into(),
+                template: Some("$1 = $0".into()),
+                category: Some(KERNEL.into()),
+                color: None,
+                inputs: vec![
+                    WzrdType {
+                        name: "value".into(),
+                        data_type: WzrdValueType::Any,
+                        order: 1,
+                    },
+                    WzrdType {
+                        name: "target".into(),
+                        data_type: WzrdValueType::Expression { value: "".into() },
+                        order: 2,
+                    },
+                ],
+                outputs: vec![],
+            },
+            WzrdNodes::Puts => WzrdNode {
+                label: "puts".into(),
+                template: Some("puts $0".into()),
+                category: Some(KERNEL.into()),
+                color: None,
+                inputs: vec![WzrdType {
+                    name: "value".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+                outputs: vec![],
+            },
+            // the statements of a branch, built by the parser with one input per statement
+            WzrdNodes::Block => WzrdNode {
+                template: None,
+                label: "Block".into(),
+                category: None,
+                color: None,
+                inputs: vec![],
+                outputs: vec![WzrdType {
+                    name: "out".into(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }],
+            },
+            WzrdNodes::Subtract => binary_operator(MATH, "-", WzrdValueType::Any),
+            WzrdNodes::Divide => binary_operator(MATH, "/", WzrdValueType::Any),
+            WzrdNodes::Modulo => binary_operator(MATH, "%", WzrdValueType::Any),
+            WzrdNodes::Power => binary_operator(MATH, "**", WzrdValueType::Any),
+            WzrdNodes::Abs => method_node(
+                MATH,
+                "abs",
+                "$0.abs",
+                vec![("value", number())],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Round => method_node(
+                MATH,
+                "round",
+                "$0.round",
+                vec![("value", number())],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::Floor => method_node(
+                MATH,
+                "floor",
+                "$0.floor",
+                vec![("value", number())],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::Ceil => method_node(
+                MATH,
+                "ceil",
+                "$0.ceil",
+                vec![("value", number())],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::Equal => binary_operator(COMPARISON, "==", WzrdValueType::Any),
+            WzrdNodes::NotEqual => binary_operator(COMPARISON, "!=", WzrdValueType::Any),
+            WzrdNodes::LessThan => binary_operator(COMPARISON, "<", WzrdValueType::Any),
+            WzrdNodes::GreaterThan => binary_operator(COMPARISON, ">", WzrdValueType::Any),
+            WzrdNodes::LessOrEqual => binary_operator(COMPARISON, "<=", WzrdValueType::Any),
+            WzrdNodes::GreaterOrEqual => binary_operator(COMPARISON, ">=", WzrdValueType::Any),
+            WzrdNodes::And => binary_operator(LOGIC, "&&", WzrdValueType::Any),
+            WzrdNodes::Or => binary_operator(LOGIC, "||", WzrdValueType::Any),
+            WzrdNodes::Not => method_node(
+                LOGIC,
+                "!",
+                "!$0",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::IsNil => method_node(
+                LOGIC,
+                "nil?",
+                "$0.nil?",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Upcase => method_node(
+                STRING,
+                "upcase",
+                "$0.upcase",
+                vec![("string", string())],
+                Some(string()),
+            ),
+            WzrdNodes::Downcase => method_node(
+                STRING,
+                "downcase",
+                "$0.downcase",
+                vec![("string", string())],
+                Some(string()),
+            ),
+            WzrdNodes::Capitalize => method_node(
+                STRING,
+                "capitalize",
+                "$0.capitalize",
+                vec![("string", string())],
+                Some(string()),
+            ),
+            WzrdNodes::Strip => method_node(
+                STRING,
+                "strip",
+                "$0.strip",
+                vec![("string", string())],
+                Some(string()),
+            ),
+            WzrdNodes::Length => method_node(
+                STRING,
+                "length",
+                "$0.length",
+                vec![("string", string())],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::StartWith => method_node(
+                STRING,
+                "start_with?",
+                "$0.start_with?($1)",
+                vec![("string", string()), ("prefix", string())],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::EndWith => method_node(
+                STRING,
+                "end_with?",
+                "$0.end_with?($1)",
+                vec![("string", string()), ("suffix", string())],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Gsub => method_node(
+                STRING,
+                "gsub",
+                "$0.gsub($1, $2)",
+                vec![
+                    ("string", string()),
+                    ("pattern", WzrdValueType::Any),
+                    ("replacement", string()),
+                ],
+                Some(string()),
+            ),
+            WzrdNodes::Split => method_node(
+                STRING,
+                "split",
+                "$0.split($1)",
+                vec![("string", string()), ("separator", string())],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Size => method_node(
+                COLLECTIONS,
+                "size",
+                "$0.size",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::IsEmpty => method_node(
+                COLLECTIONS,
+                "empty?",
+                "$0.empty?",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Include => method_node(
+                COLLECTIONS,
+                "include?",
+                "$0.include?($1)",
+                vec![
+                    ("collection", WzrdValueType::Any),
+                    ("value", WzrdValueType::Any),
+                ],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Join => method_node(
+                COLLECTIONS,
+                "join",
+                "$0.join($1)",
+                vec![("collection", WzrdValueType::Any), ("separator", string())],
+                Some(string()),
+            ),
+            WzrdNodes::First => method_node(
+                COLLECTIONS,
+                "first",
+                "$0.first",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Last => method_node(
+                COLLECTIONS,
+                "last",
+                "$0.last",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Max => method_node(
+                COLLECTIONS,
+                "max",
+                "$0.max",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Min => method_node(
+                COLLECTIONS,
+                "min",
+                "$0.min",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Sum => method_node(
+                COLLECTIONS,
+                "sum",
+                "$0.sum",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Sort => method_node(
+                COLLECTIONS,
+                "sort",
+                "$0.sort",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Uniq => method_node(
+                COLLECTIONS,
+                "uniq",
+                "$0.uniq",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::Reverse => method_node(
+                COLLECTIONS,
+                "reverse",
+                "$0.reverse",
+                vec![("collection", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::ToS => method_node(
+                CONVERSION,
+                "to_s",
+                "$0.to_s",
+                vec![("value", WzrdValueType::Any)],
+                Some(string()),
+            ),
+            WzrdNodes::ToI => method_node(
+                CONVERSION,
+                "to_i",
+                "$0.to_i",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Integer { value: 0 }),
+            ),
+            WzrdNodes::ToF => method_node(
+                CONVERSION,
+                "to_f",
+                "$0.to_f",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Float { value: 0.0 }),
+            ),
+            WzrdNodes::ToA => method_node(
+                CONVERSION,
+                "to_a",
+                "$0.to_a",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::ToSym => method_node(
+                CONVERSION,
+                "to_sym",
+                "$0.to_sym",
+                vec![("value", WzrdValueType::Any)],
+                Some(WzrdValueType::Any),
+            ),
+            WzrdNodes::P => method_node(
+                KERNEL,
+                "p",
+                "p $0",
+                vec![("value", WzrdValueType::Any)],
+                None,
+            ),
+            WzrdNodes::Print => method_node(
+                KERNEL,
+                "print",
+                "print $0",
+                vec![("value", WzrdValueType::Any)],
+                None,
+            ),
+            WzrdNodes::Raise => method_node(
+                KERNEL,
+                "raise",
+                "raise $0",
+                vec![("error", WzrdValueType::Any)],
+                None,
+            ),
+        }
+    }
+}
+
+/// Every node with a category is offered in the node finder.
+pub fn create_std_nodes() -> Vec<WzrdNode> {
+    all::<WzrdNodes>()
+        .map(|node_enum| node_enum.node())
+        .filter(|node| node.category.is_some())
+        .collect()
+}
@@ -0,0 +1,257 @@
+use crate::graph_core::node::structs::{WzrdNode, WzrdNodeTemplates};
+#[cfg(feature = "persistence")]
+use crate::graph_core::node::structs::{WzrdType, WzrdValueType};
+#[cfg(feature = "persistence")]
+use lazy_static::lazy_static;
+#[cfg(feature = "persistence")]
+use regex::Regex;
+#[cfg(feature = "persistence")]
+use serde::Deserialize;
+
+/// File next to the project in which teams declare their own nodes.
+pub const CUSTOM_NODES_FILE: &str = "wzrd_nodes.toml";
+
+#[cfg(feature = "persistence")]
+#[derive(Deserialize)]
+struct CustomNodeFile {
+    #[serde(default, rename = "node")]
+    nodes: Vec<CustomNode>,
+}
+
+#[cfg(feature = "persistence")]
+#[derive(Deserialize)]
+struct CustomNode {
+    label: String,
+    template: String,
+    category: Option<String>,
+    color: Option<String>,
+    #[serde(default)]
+    inputs: Vec<CustomSocket>,
+    #[serde(default)]
+    outputs: Vec<CustomSocket>,
+}
+
+#[cfg(feature = "persistence")]
+#[derive(Deserialize)]
+struct CustomSocket {
+    name: String,
+    #[serde(rename = "type", default = "any_type")]
+    data_type: String,
+}
+
+#[cfg(feature = "persistence")]
+fn any_type() -> String {
+    "any".into()
+}
+
+#[cfg(feature = "persistence")]
+fn parse_value_type(data_type: &str) -> Result<WzrdValueType, String> {
+    match data_type.to_lowercase().as_str() {
+        "any" => Ok(WzrdValueType::Any),
+        "number" => Ok(WzrdValueType::Number {
+            value: 0.0,
+            decimal: false,
+        }),
+        "integer" => Ok(WzrdValueType::Integer { value: 0 }),
+        "float" => Ok(WzrdValueType::Float { value: 0.0 }),
+        "string" => Ok(WzrdValueType::String { value: "".into() }),
+        "expression" => Ok(WzrdValueType::Expression { value: "".into() }),
+        "boolean" => Ok(WzrdValueType::Boolean { value: false }),
+        "nil" => Ok(WzrdValueType::Nil),
+        "symbol" => Ok(WzrdValueType::Symbol { value: "".into() }),
+        "range" => Ok(WzrdValueType::Range {
+            start: 0,
+            end: 0,
+            exclusive: false,
+        }),
+        "regexp" => Ok(WzrdValueType::Regexp {
+            value: "".into(),
+            options: "".into(),
+        }),
+        other => Err(format!("unknown type `{other}`")),
+    }
+}
+
+#[cfg(feature = "persistence")]
+fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.trim_start_matches('#');
+    let channel = |index: usize| {
+        hex.get(index..index + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("`{color}` is not a #rrggbb colour")),
+    }
+}
+
+#[cfg(feature = "persistence")]
+fn parse_sockets(sockets: &[CustomSocket]) -> Result<Vec<WzrdType>, String> {
+    sockets
+        .iter()
+        .enumerate()
+        .map(|(i, socket)| {
+            Ok(WzrdType {
+                name: socket.name.clone(),
+                data_type: parse_value_type(&socket.data_type)
+                    .map_err(|err| format!("socket `{}`: {err}", socket.name))?,
+                order: i as u8 + 1,
+            })
+        })
+        .collect()
+}
+
+/// Every `$N` placeholder has to be backed by the input at position `N`.
+#[cfg(feature = "persistence")]
+fn validate_template(template: &str, inputs: &[WzrdType]) -> Result<(), String> {
+    lazy_static! {
+        static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\$(\d+)").unwrap();
+    }
+    for captures in PLACEHOLDER_REGEX.captures_iter(template) {
+        let index: usize = captures[1].parse().unwrap_or(usize::MAX);
+        if index >= inputs.len() {
+            return Err(format!(
+                "template references ${:} but only {:} input(s) are defined",
+                &captures[1],
+                inputs.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl WzrdNodeTemplates {
+    pub fn find(&self, label: &str) -> Option<&WzrdNode> {
+        self.0.iter().find(|node| node.label == label)
+    }
+
+    /// Adds the nodes declared in a [`CUSTOM_NODES_FILE`] and returns a message for every node
+    /// that could not be added.
+    #[cfg(feature = "persistence")]
+    pub fn load_custom_nodes(&mut self, source: &str) -> Vec<String> {
+        let file: CustomNodeFile = match toml::from_str(source) {
+            Ok(file) => file,
+            Err(err) => return vec![format!("{CUSTOM_NODES_FILE}: {err}")],
+        };
+
+        let mut errors = vec![];
+        for node in file.nodes {
+            let parsed = (|| {
+                if self.find(&node.label).is_some() {
+                    return Err("a node with this label already exists".to_string());
+                }
+                let inputs = parse_sockets(&node.inputs)?;
+                let outputs = parse_sockets(&node.outputs)?;
+                validate_template(&node.template, &inputs)?;
+                let color = node.color.as_deref().map(parse_color).transpose()?;
+                Ok((inputs, outputs, color))
+            })();
+
+            match parsed {
+                Ok((inputs, outputs, color)) => {
+                    self.create_node(
+                        &node.label,
+                        Some(node.template),
+                        node.category.or(Some("Custom".into())),
+                        color,
+                        inputs,
+                        outputs,
+                    );
+                }
+                Err(err) => errors.push(format!("{CUSTOM_NODES_FILE}: `{}`: {err}", node.label)),
+            }
+        }
+        errors
+    }
+
+    /// Custom nodes are read with serde, without the `persistence` feature they are refused.
+    #[cfg(not(feature = "persistence"))]
+    pub fn load_custom_nodes(&mut self, _source: &str) -> Vec<String> {
+        vec![format!(
+            "{CUSTOM_NODES_FILE}: custom nodes need the persistence feature"
+        )]
+    }
+}
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use super::*;
+    use crate::graph_core::node::create_std_nodes;
+
+    fn load(source: &str) -> (WzrdNodeTemplates, Vec<String>) {
+        let mut templates = WzrdNodeTemplates(create_std_nodes());
+        let errors = templates.load_custom_nodes(source);
+        (templates, errors)
+    }
+
+    #[test]
+    fn declared_nodes_are_added_to_the_custom_category() {
+        let (templates, errors) = load(
+            r##"
+            [[node]]
+            label = "clamp"
+            template = "$0.clamp($1, $2)"
+            color = "#ff8000"
+            inputs = [{ name = "value", type = "number" }, { name = "min" }, { name = "max" }]
+            outputs = [{ name = "out", type = "Number" }]
+            "##,
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        let node = templates.find("clamp").unwrap();
+        assert_eq!(node.category.as_deref(), Some("Custom"));
+        assert_eq!(node.color, Some([255, 128, 0]));
+        assert_eq!(node.inputs.len(), 3);
+        assert!(matches!(node.inputs[1].data_type, WzrdValueType::Any));
+        assert!(matches!(
+            node.outputs[0].data_type,
+            WzrdValueType::Number { .. }
+        ));
+    }
+
+    #[test]
+    fn invalid_nodes_are_reported_and_the_others_added() {
+        let (templates, errors) = load(
+            r#"
+            [[node]]
+            label = "shout"
+            template = "$0.upcase + $1"
+            inputs = [{ name = "text", type = "string" }]
+
+            [[node]]
+            label = "odd"
+            template = "$0"
+            inputs = [{ name = "value", type = "complex" }]
+
+            [[node]]
+            label = "tint"
+            template = "$0"
+            color = "orange"
+            inputs = [{ name = "value" }]
+
+            [[node]]
+            label = "puts"
+            template = "puts $0"
+            inputs = [{ name = "value" }]
+
+            [[node]]
+            label = "twice"
+            template = "($0 * 2)"
+            inputs = [{ name = "value", type = "integer" }]
+            "#,
+        );
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert!(errors[0].contains("references $1"));
+        assert!(errors[1].contains("unknown type `complex`"));
+        assert!(errors[2].contains("not a #rrggbb colour"));
+        assert!(errors[3].contains("already exists"));
+        assert!(templates.find("twice").is_some());
+        assert!(templates.find("shout").is_none());
+    }
+
+    #[test]
+    fn unreadable_files_are_reported() {
+        let (_, errors) = load("[[node]\nlabel = ");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with(CUSTOM_NODES_FILE));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::graph_core::wzrd_node_graph::fragment::WzrdGraphFragment;
+use egui_node_graph::NodeId;
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Default, Clone)]
+pub struct WzrdNodeTemplates(pub Vec<WzrdNode>);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdType {
+    pub name: String,
+    // pub data_type: WzrdNodeDataType,
+    pub data_type: WzrdValueType,
+    pub order: u8,
+    // pub initial_value: Option<WzrdValueType>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdNode {
+    pub template: Option<String>,
+    pub label: String,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub category: Option<String>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub color: Option<[u8; 3]>,
+    pub inputs: Vec<WzrdType>,
+    pub outputs: Vec<WzrdType>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdNodeData {
+    pub template: WzrdNode,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub subgraph: Option<WzrdSubgraph>,
+    /// Place of the node in the order statements are generated in, the order of the code the
+    /// node was parsed from or else the order it was added in.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub sequence: u64,
+}
+
+/// The nodes collapsed into a subgraph node. `inputs` and `outputs` point at the sockets inside
+/// `fragment` behind each socket of the subgraph node.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdSubgraph {
+    pub fragment: WzrdGraphFragment,
+    pub inputs: Vec<(usize, usize)>,
+    pub outputs: Vec<(usize, usize)>,
+}
+
+/// Type of a socket. Output sockets carry the type inferred for their value, see
+/// `WzrdNodeGraph::infer_types`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum WzrdNodeDataType {
+    Number,
+    Integer,
+    Float,
+    Boolean,
+    Nil,
+    Symbol,
+    Range,
+    Regexp,
+    Any,
+    String,
+    Expression,
+    None,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum WzrdValueType {
+    // Object { value: Option<dyn Any> },
+    String {
+        value: String,
+    },
+    Expression {
+        value: String,
+    },
+    Integer {
+        value: i64,
+    },
+    /// Either an integer or a float, `decimal` tells which of the two it is written as.
+    Number {
+        value: f64,
+        decimal: bool,
+    },
+    Float {
+        value: f64,
+    },
+    Boolean {
+        value: bool,
+    },
+    Nil,
+    Symbol {
+        value: String,
+    },
+    Range {
+        start: i64,
+        end: i64,
+        exclusive: bool,
+    },
+    Regexp {
+        value: String,
+        options: String,
+    },
+    Any,
+}
+
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct WzrdGraphState {
+    pub project_methods: HashMap<String, WzrdProjectMethod>,
+    /// Parameters of the method shown in the graph.
+    pub parameters: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WzrdResponse {
+    OpenDefinition(NodeId),
+    OpenSubgraph(NodeId),
+    ExpandSubgraph(NodeId),
+    /// A constant of the node was edited.
+    ValueChanged(NodeId),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdFunction {
+    pub name: String,
+    pub arguments: Vec<String>,
+    /// The parameters as they were written, with their defaults, splats and keywords. `None`
+    /// writes the names of `arguments`.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub signature: Option<String>,
+}
+
+/// A method defined somewhere in the workspace that can be called from the graph.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdProjectMethod {
+    pub function: WzrdFunction,
+    /// `None` for the document that is being edited.
+    pub path: Option<String>,
+    pub line: usize,
+    pub source: String,
+}
@@ -0,0 +1,1764 @@
+use crate::graph_core::node::structs::{
+    WzrdFunction, WzrdGraphState, WzrdNode, WzrdNodeData, WzrdNodeDataType, WzrdNodeTemplates,
+    WzrdResponse, WzrdSubgraph, WzrdType, WzrdValueType,
+};
+use crate::graph_core::node::{create_std_nodes, WzrdCallStyle, WzrdNodes};
+use egui_node_graph::{
+    Graph, GraphEditorState, GraphResponse, InputId, InputParamKind, Node, NodeDataTrait, NodeId,
+    NodeResponse, NodeTemplateTrait, OutputId,
+};
+use emath::{Pos2, Vec2};
+use lazy_static::lazy_static;
+use lib_ruby_parser::{Parser, ParserOptions, ParserResult};
+use log::{debug, info, warn};
+use regex::Regex;
+use std::collections::{HashMap, HashSet, LinkedList};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+mod document;
+pub mod fragment;
+#[cfg(feature = "persistence")]
+mod graph_file;
+mod layout;
+mod outline;
+mod project_methods;
+mod refactor;
+mod saved_layout;
+mod subgraph;
+mod types;
+
+pub use document::WzrdDocument;
+#[cfg(feature = "persistence")]
+pub use graph_file::{WzrdGraphFile, GRAPH_FILE_VERSION};
+pub use project_methods::PROJECT;
+use project_methods::{parameters_source, parse_parameters};
+pub use saved_layout::{WzrdSavedLayout, LAYOUT_COMMENT};
+pub use subgraph::{WzrdOpenSubgraph, SUBGRAPH};
+pub use types::{WzrdConnectionCheck, WzrdPendingConversion};
+
+type RNode = lib_ruby_parser::Node;
+type Def = lib_ruby_parser::nodes::Def;
+
+pub const PERSISTENCE_KEY: &str = "egui_node_graph";
+
+pub type WzrdGraph = Graph<WzrdNodeData, WzrdNodeDataType, WzrdValueType>;
+pub type WzrdGraphResponse = GraphResponse<WzrdResponse, WzrdNodeData>;
+
+pub type WzrdEditorState =
+    GraphEditorState<WzrdNodeData, WzrdNodeDataType, WzrdValueType, WzrdNode, WzrdGraphState>;
+
+pub type NodeCache = HashMap<NodeId, String>;
+
+#[derive(Default)]
+pub struct WzrdNodeGraph {
+    pub state: WzrdEditorState,
+    pub user_state: WzrdGraphState,
+    pub node_templates: WzrdNodeTemplates,
+    pub function_stack: LinkedList<WzrdFunction>,
+    pub template_errors: Vec<String>,
+    pub open_subgraphs: Vec<WzrdOpenSubgraph>,
+    /// The code around the edited method, `None` for a graph that is not part of a document.
+    pub document: Option<WzrdDocument>,
+    /// Methods created by extract method, they are put after the edited method.
+    pub extracted_methods: Vec<String>,
+    /// Nodes moved by hand, [`WzrdNodeGraph::format_new_nodes`] leaves them where they are.
+    pub pinned_nodes: HashSet<NodeId>,
+    /// Nodes built from code that still sit where they were created.
+    pub unplaced_nodes: HashSet<NodeId>,
+    /// The code being parsed, what the graph has no node for is kept as it was written.
+    parsed_code: String,
+    /// Statements and comments of the document the graph has no node for. The document is not
+    /// written while there are any, writing it would drop them.
+    pub unsupported_code: Vec<String>,
+
+    pub format_requested: Arc<Mutex<bool>>,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // only read through the debug output
+enum ParsedValueType {
+    Int(i128),
+    String(String),
+}
+
+#[derive(Debug, Clone)]
+struct ParsedWzrdNode {
+    wzrd_node: WzrdNode,
+    value: Option<ParsedValueType>, // keep for debug purposes for now
+    // positional, `None` leaves the input at that position unconnected
+    inputs: Vec<Option<ParsedWzrdNode>>,
+}
+
+impl ParsedWzrdNode {
+    /// The literal of a parsed Constant, which can be inlined into the socket it feeds.
+    fn folded_constant(&self) -> Option<WzrdValueType> {
+        if self.wzrd_node.label != "Constant" || !self.inputs.is_empty() {
+            return None;
+        }
+        self.wzrd_node
+            .inputs
+            .first()
+            .map(|input| input.data_type.clone())
+            .filter(|value| !matches!(value, WzrdValueType::Any))
+    }
+}
+
+impl WzrdNodeGraph {
+    pub fn new() -> Self {
+        let standard_nodes: Vec<WzrdNode> = create_std_nodes();
+
+        Self {
+            state: WzrdEditorState::default(),
+            user_state: WzrdGraphState::default(),
+            node_templates: WzrdNodeTemplates(standard_nodes),
+            format_requested: Arc::new(Mutex::new(false)),
+            function_stack: LinkedList::new(),
+            template_errors: vec![],
+            open_subgraphs: vec![],
+            document: None,
+            extracted_methods: vec![],
+            pinned_nodes: HashSet::new(),
+            unplaced_nodes: HashSet::new(),
+            parsed_code: String::new(),
+            unsupported_code: vec![],
+        }
+    }
+
+    /// Registers the user-defined nodes from `source`, see [`CUSTOM_NODES_FILE`].
+    pub fn load_custom_nodes(&mut self, source: &str) {
+        let errors = self.node_templates.load_custom_nodes(source);
+        for error in errors.iter() {
+            warn!("{error}");
+        }
+        self.template_errors.extend(errors);
+    }
+
+    fn find_template(&self, label: &str) -> Option<WzrdNode> {
+        self.node_templates
+            .find(label)
+            .cloned()
+            .or_else(|| WzrdNodes::find_node(label))
+    }
+
+    /// The node for a call of `name` written in `style` with `inputs` inputs, the receiver
+    /// included. Calls no node fits keep their shape in a generic call node.
+    fn find_call_template(&self, name: &str, style: WzrdCallStyle, inputs: usize) -> WzrdNode {
+        self.node_templates
+            .find(name)
+            .cloned()
+            .into_iter()
+            .chain(WzrdNodes::find_node(name))
+            .find(|node| node.call_style() == Some(style) && node.inputs.len() == inputs)
+            .unwrap_or_else(|| {
+                debug!("no node for {name} with {inputs} input(s), keeping it as a call");
+                WzrdNode::generic_call(name, style, inputs)
+            })
+    }
+
+    /// The Ruby code of the graph within the rest of the document. Fails if the graph can't be
+    /// generated, for example because it loops back, rather than writing broken code.
+    pub fn evaluate_graph(&mut self, cache: &mut NodeCache) -> anyhow::Result<String> {
+        struct Evaluator<'a> {
+            graph: &'a WzrdGraph,
+            cache: &'a mut NodeCache,
+            consumers: HashMap<OutputId, usize>,
+            reserved_names: HashSet<String>,
+            hoisted: Vec<String>,
+            /// The branches of conditionals being evaluated, innermost last, with the nodes only
+            /// the branch consumes and the values hoisted into it.
+            branches: Vec<(HashSet<NodeId>, Vec<String>)>,
+            /// Values of the outputs of evaluated subgraph nodes.
+            subgraph_values: HashMap<OutputId, String>,
+        }
+
+        impl<'a> Evaluator<'a> {
+            fn new(
+                graph: &'a WzrdGraph,
+                cache: &'a mut NodeCache,
+                reserved_names: HashSet<String>,
+            ) -> Self {
+                let mut consumers: HashMap<OutputId, usize> = HashMap::new();
+                for (_, output_id) in graph.connections.iter() {
+                    *consumers.entry(*output_id).or_insert(0) += 1;
+                }
+
+                Self {
+                    graph,
+                    cache,
+                    consumers,
+                    reserved_names,
+                    hoisted: vec![],
+                    branches: vec![],
+                    subgraph_values: HashMap::new(),
+                }
+            }
+
+            fn apply_template(&self, template: &str, input_values: &[String]) -> String {
+                lazy_static! {
+                    static ref ARGUMENT_REGEX: Regex = Regex::new(r"\$(\d+)").unwrap();
+                }
+                ARGUMENT_REGEX
+                    .replace_all(template, |captures: &regex::Captures| {
+                        captures[1]
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|index| input_values.get(index))
+                            .cloned()
+                            .unwrap_or_default()
+                    })
+                    .into_owned()
+            }
+
+            /// Constants and variables are cheap to repeat, everything else that feeds more
+            /// than one input is worth a local variable.
+            fn is_shared(&self, node: &Node<WzrdNodeData>) -> bool {
+                let label = &node.user_data.template.label;
+                if label == "Constant" || label == "Variable" {
+                    return false;
+                }
+                node.outputs
+                    .iter()
+                    .map(|(_, output_id)| self.consumers.get(output_id).copied().unwrap_or(0))
+                    .sum::<usize>()
+                    > 1
+            }
+
+            /// Whether a value of `node` is pasted into an expression rather than taking the
+            /// place of a statement, like in a branch of an If, a Block or the value of an
+            /// assignment or return. Multi-line values
+            /// like an `if` statement are assigned to a local variable first then.
+            fn is_used_in_expression(&self, node: &Node<WzrdNodeData>) -> bool {
+                let graph = self.graph;
+                graph.connections.iter().any(|(input_id, output_id)| {
+                    if graph.get_output(*output_id).node != node.id {
+                        return false;
+                    }
+                    let consumer = &graph[graph.inputs[input_id].node];
+                    match consumer.user_data.template.label.as_str() {
+                        "Block" | "Assignment" | "output" => false,
+                        "If" => consumer.inputs.first().map(|(_, id)| *id) == Some(input_id),
+                        _ => true,
+                    }
+                })
+            }
+
+            /// Early returns and missing branches can't be expressed with a ternary.
+            fn conditional_statement(&self, input_values: &[String]) -> String {
+                let indent = |code: &str| {
+                    code.lines()
+                        .map(|line| format!("    {line}"))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                };
+                let value = |index: usize| input_values.get(index).cloned().unwrap_or_default();
+
+                let mut lines = vec![format!("if {}", value(0))];
+                if !value(1).is_empty() {
+                    lines.push(indent(&value(1)));
+                }
+                if !value(2).is_empty() {
+                    lines.push("else".into());
+                    lines.push(indent(&value(2)));
+                }
+                lines.push("end".into());
+                lines.join("\n")
+            }
+
+            /// Nodes only consumed through `input_id`, directly or through each other. Their
+            /// values are only needed when the branch fed by `input_id` is taken.
+            fn branch_nodes(&self, input_id: InputId) -> HashSet<NodeId> {
+                let graph = self.graph;
+                let mut nodes: HashSet<NodeId> = HashSet::new();
+                let mut stack: Vec<NodeId> = graph
+                    .connection(input_id)
+                    .map(|output_id| graph.get_output(output_id).node)
+                    .into_iter()
+                    .collect();
+                while let Some(node_id) = stack.pop() {
+                    if nodes.insert(node_id) {
+                        stack.extend(
+                            graph[node_id]
+                                .inputs
+                                .iter()
+                                .filter_map(|(_, input_id)| graph.connection(*input_id))
+                                .map(|output_id| graph.get_output(output_id).node),
+                        );
+                    }
+                }
+                // a node also consumed outside the branch takes what it depends on along
+                loop {
+                    let escaping: Vec<NodeId> = nodes
+                        .iter()
+                        .copied()
+                        .filter(|node_id| {
+                            graph.connections.iter().any(|(consumer, output_id)| {
+                                graph.get_output(*output_id).node == *node_id
+                                    && consumer != input_id
+                                    && !nodes.contains(&graph.inputs[consumer].node)
+                            })
+                        })
+                        .collect();
+                    if escaping.is_empty() {
+                        return nodes;
+                    }
+                    for node_id in escaping {
+                        nodes.remove(&node_id);
+                    }
+                }
+            }
+
+            /// Evaluates a branch of a conditional. Values hoisted for the branch alone are
+            /// computed at its start, so only when it is taken. Returns whether the branch got
+            /// statements of its own.
+            fn evaluate_branch(
+                &mut self,
+                input_id: InputId,
+                visiting: &mut HashSet<NodeId>,
+            ) -> anyhow::Result<(String, bool)> {
+                self.branches.push((self.branch_nodes(input_id), vec![]));
+                let value = self.evaluate_input(input_id, visiting);
+                let (_, mut statements) = self.branches.pop().unwrap_or_default();
+                let value = value?;
+                if statements.is_empty() {
+                    return Ok((value, false));
+                }
+                statements.push(value);
+                Ok((statements.join("\n"), true))
+            }
+
+            /// Hoists a statement computing the value of `node_id` into the innermost branch
+            /// that alone consumes the node, or in front of the current statement.
+            fn hoist(&mut self, node_id: NodeId, statement: String) {
+                match self
+                    .branches
+                    .iter_mut()
+                    .rev()
+                    .find(|(nodes, _)| nodes.contains(&node_id))
+                {
+                    Some((_, hoisted)) => hoisted.push(statement),
+                    None => self.hoisted.push(statement),
+                }
+            }
+
+            fn next_temporary(&mut self) -> String {
+                let mut index = 1;
+                loop {
+                    let name = if index == 1 {
+                        String::from("tmp")
+                    } else {
+                        format!("tmp{index}")
+                    };
+                    if self.reserved_names.insert(name.clone()) {
+                        return name;
+                    }
+                    index += 1;
+                }
+            }
+
+            fn evaluate_input(
+                &mut self,
+                input_id: InputId,
+                visiting: &mut HashSet<NodeId>,
+            ) -> anyhow::Result<String> {
+                let graph = self.graph;
+                if let Some(other_output_id) = graph.connection(input_id) {
+                    self.evaluate_output(other_output_id, visiting)
+                } else {
+                    //node has a constant value, so it's of WzrdValueType
+                    Ok(graph.inputs[input_id].value.to_literal())
+                }
+            }
+
+            fn evaluate_output(
+                &mut self,
+                output_id: OutputId,
+                visiting: &mut HashSet<NodeId>,
+            ) -> anyhow::Result<String> {
+                let graph = self.graph;
+                let node = &graph[graph.get_output(output_id).node];
+                match &node.user_data.subgraph {
+                    Some(subgraph) => {
+                        if !self.cache.contains_key(&node.id) {
+                            let statements = self.evaluate_subgraph(node, subgraph, visiting)?;
+                            if !statements.is_empty() {
+                                self.hoist(node.id, statements);
+                            }
+                        }
+                        Ok(self
+                            .subgraph_values
+                            .get(&output_id)
+                            .cloned()
+                            .unwrap_or_default())
+                    }
+                    None => self.evaluate_node(node.id, visiting),
+                }
+            }
+
+            /// Evaluates the nodes inside a subgraph once, with the outer values of its inputs
+            /// pasted in as expressions. The values of the outputs used outside are kept in
+            /// `subgraph_values`, the statements of all other sinks are returned.
+            fn evaluate_subgraph(
+                &mut self,
+                node: &Node<WzrdNodeData>,
+                subgraph: &WzrdSubgraph,
+                visiting: &mut HashSet<NodeId>,
+            ) -> anyhow::Result<String> {
+                if !visiting.insert(node.id) {
+                    anyhow::bail!("cycle detected at node {:}", node.label);
+                }
+                let mut input_values: Vec<String> = vec![];
+                for (_, input_id) in node.inputs.iter() {
+                    input_values.push(self.evaluate_input(*input_id, visiting)?);
+                }
+                visiting.remove(&node.id);
+
+                subgraph.validate()?;
+                let mut inner_graph = WzrdGraph::new();
+                let inserted = subgraph.fragment.insert(
+                    &mut inner_graph,
+                    &mut WzrdGraphState::default(),
+                    Vec2::ZERO,
+                )?;
+                for ((node_index, input_index), value) in subgraph.inputs.iter().zip(input_values) {
+                    let input_id = inner_graph[inserted[*node_index].0].inputs[*input_index].1;
+                    inner_graph.inputs[input_id].value = WzrdValueType::Expression { value };
+                }
+
+                let used_outputs: Vec<(OutputId, OutputId)> = node
+                    .outputs
+                    .iter()
+                    .zip(subgraph.outputs.iter())
+                    .filter(|((_, output_id), _)| self.consumers.contains_key(output_id))
+                    .map(|((_, output_id), (node_index, output_index))| {
+                        let inner_node = &inner_graph[inserted[*node_index].0];
+                        (*output_id, inner_node.outputs[*output_index].1)
+                    })
+                    .collect();
+                let mut sinks: Vec<NodeId> = inner_graph
+                    .iter_nodes()
+                    .filter(|node_id| {
+                        !inner_graph[*node_id].outputs.iter().any(|(_, output_id)| {
+                            inner_graph.connections.values().any(|id| id == output_id)
+                                || used_outputs.iter().any(|(_, inner)| inner == output_id)
+                        })
+                    })
+                    .collect();
+                sinks.sort_by_key(|node_id| (inner_graph[*node_id].user_data.sequence, *node_id));
+
+                let mut inner_cache: NodeCache = HashMap::new();
+                let mut inner =
+                    Evaluator::new(&inner_graph, &mut inner_cache, self.reserved_names.clone());
+                let mut statements = vec![];
+                for sink in sinks {
+                    let statement = inner.evaluate_node(sink, &mut HashSet::new())?;
+                    statements.append(&mut inner.hoisted);
+                    statements.push(statement);
+                }
+                let mut values = vec![];
+                for (output_id, inner_output_id) in used_outputs {
+                    let value = inner.evaluate_output(inner_output_id, &mut HashSet::new())?;
+                    statements.append(&mut inner.hoisted);
+                    values.push((output_id, value));
+                }
+                self.reserved_names.extend(inner.reserved_names);
+
+                for (output_id, value) in values {
+                    // an output used several times is computed once, like a shared node
+                    let is_name = value.chars().all(|c| c.is_alphanumeric() || c == '_');
+                    let value = if self.consumers[&output_id] > 1 && !is_name {
+                        let name = self.next_temporary();
+                        statements.push(format!("{name} = {value}"));
+                        name
+                    } else {
+                        value
+                    };
+                    self.subgraph_values.insert(output_id, value);
+                }
+                self.cache.insert(node.id, String::new());
+                Ok(statements
+                    .into_iter()
+                    .filter(|statement| !statement.is_empty())
+                    .collect::<Vec<String>>()
+                    .join("\n"))
+            }
+
+            fn evaluate_node(
+                &mut self,
+                node_id: NodeId,
+                visiting: &mut HashSet<NodeId>,
+            ) -> anyhow::Result<String> {
+                if let Some(cached_out) = self.cache.get(&node_id) {
+                    return Ok(cached_out.clone());
+                }
+
+                let graph = self.graph;
+                let node: &Node<WzrdNodeData> = &graph[node_id];
+                if let Some(subgraph) = &node.user_data.subgraph {
+                    return self.evaluate_subgraph(node, subgraph, visiting);
+                }
+                // a node that is already on the evaluation path means the graph loops back
+                if !visiting.insert(node_id) {
+                    anyhow::bail!("cycle detected at node {:}", node.label);
+                }
+
+                let is_conditional = node.user_data.template.label == "If";
+                let is_block = node.user_data.template.label == "Block";
+                let mut has_branch_statements = false;
+                let mut input_values: Vec<String> = vec![];
+                for (index, (_, input_id)) in node.inputs.iter().enumerate() {
+                    // every statement of a block but the last runs before its value is used
+                    if is_block && index > 0 {
+                        let statement = input_values.remove(0);
+                        self.hoist(node_id, statement);
+                    }
+                    let value = if is_conditional && index > 0 {
+                        let (value, has_statements) = self.evaluate_branch(*input_id, visiting)?;
+                        has_branch_statements |= has_statements;
+                        value
+                    } else {
+                        self.evaluate_input(*input_id, visiting)?
+                    };
+                    input_values.push(value);
+                }
+                visiting.remove(&node_id);
+                let returning_inputs: Vec<bool> = node
+                    .inputs
+                    .iter()
+                    .map(|(_, input_id)| {
+                        graph.connection(*input_id).is_some_and(|output_id| {
+                            let other_node_id = graph.get_output(output_id).node;
+                            graph[other_node_id].user_data.template.label == "output"
+                        })
+                    })
+                    .collect();
+
+                let is_branching_statement = is_conditional
+                    && (has_branch_statements
+                        || returning_inputs.contains(&true)
+                        || input_values.iter().skip(1).any(|value| value.is_empty()));
+
+                let expression = match &node.user_data.template.template {
+                    Some(_) if is_branching_statement => self.conditional_statement(&input_values),
+                    Some(template) => self.apply_template(template, &input_values),
+                    None => match &node.user_data.template {
+                        WzrdNode {
+                            ref label, outputs, ..
+                        } if label == "Variable" => outputs
+                            .first()
+                            .map(|output| output.name.clone())
+                            .unwrap_or("".into()),
+                        _ => input_values.first().cloned().unwrap_or_default(),
+                    },
+                };
+
+                // hoisted values are computed once, before the first statement or branch that
+                // needs them
+                let value = if self.is_shared(node)
+                    || (expression.contains('\n') && self.is_used_in_expression(node))
+                {
+                    let name = self.next_temporary();
+                    self.hoist(node_id, format!("{name} = {expression}"));
+                    name
+                } else {
+                    expression
+                };
+
+                self.cache.insert(node_id, value.clone());
+                Ok(value)
+            }
+        }
+
+        if let Some(code) = self.unsupported_code.first() {
+            anyhow::bail!("the document has code the graph can't show: {code}");
+        }
+        // a graph without nodes is an empty method or script, not an error
+        let roots = self.find_root_nodes();
+
+        let orphans = self.find_orphan_nodes();
+        for orphan in orphans.iter() {
+            warn!(
+                "{:} is not connected to anything and will be dropped from the generated code",
+                self.state.graph[*orphan].label
+            );
+        }
+
+        let reserved_names: HashSet<String> = self
+            .function_stack
+            .back()
+            .map(|function| function.arguments.iter().cloned().collect())
+            .unwrap_or_default();
+        let mut evaluator = Evaluator::new(&self.state.graph, cache, reserved_names);
+        let mut statements: Vec<String> = vec![];
+        for root in roots.into_iter().filter(|root| !orphans.contains(root)) {
+            let result = evaluator.evaluate_node(root, &mut HashSet::new())?;
+            statements.append(&mut evaluator.hoisted);
+            statements.push(result);
+        }
+        let lines: Vec<&str> = statements
+            .iter()
+            .flat_map(|statement| statement.lines())
+            .collect();
+
+        let mut document = if let Some(function_signature) = self.function_stack.back() {
+            // only the body of a method is indented, statements of a script are not
+            let code_body: String = lines.iter().map(|line| format!("    {line}\n")).collect();
+            let arguments = function_signature
+                .signature
+                .clone()
+                .unwrap_or_else(|| function_signature.arguments.join(", "));
+            format!(
+                "def {:}{:}\n{code_body}end\n",
+                function_signature.name,
+                if arguments.is_empty() {
+                    String::from("")
+                } else {
+                    format!("({arguments})")
+                }
+            )
+        } else {
+            lines.join("\n")
+        };
+
+        let methods: Vec<&str> = self
+            .extracted_methods
+            .iter()
+            .filter_map(|name| self.user_state.project_methods.get(name))
+            .map(|method| method.source.as_str())
+            .collect();
+        if let Some(around) = &self.document {
+            return Ok(around.assemble(&document, &methods));
+        }
+        for method in methods {
+            if !document.ends_with('\n') {
+                document.push('\n');
+            }
+            document.push('\n');
+            document.push_str(method);
+        }
+        Ok(document)
+    }
+
+    pub fn consumers_of(&self, output_id: OutputId) -> Vec<InputId> {
+        self.state
+            .graph
+            .connections
+            .iter()
+            .filter(|(_, connected_output_id)| **connected_output_id == output_id)
+            .map(|(input_id, _)| input_id)
+            .collect()
+    }
+
+    /// Removes a node from the graph together with everything the editor keeps about it.
+    /// Returns the node and the connections it had.
+    pub fn remove_node(
+        &mut self,
+        node_id: NodeId,
+    ) -> (Node<WzrdNodeData>, Vec<(InputId, OutputId)>) {
+        let removed = self.state.graph.remove_node(node_id);
+        self.state.node_positions.remove(node_id);
+        // Make sure to not leave references to old nodes hanging
+        self.state.node_order.retain(|id| *id != node_id);
+        self.state.selected_nodes.retain(|id| *id != node_id);
+        self.pinned_nodes.remove(&node_id);
+        self.unplaced_nodes.remove(&node_id);
+        removed
+    }
+
+    /// Deletes the nodes that may be deleted, answering with the same responses the close button
+    /// of a node produces.
+    pub fn delete_nodes(
+        &mut self,
+        node_ids: &[NodeId],
+    ) -> Vec<NodeResponse<WzrdResponse, WzrdNodeData>> {
+        let mut responses = vec![];
+        for node_id in node_ids.iter().copied() {
+            let node = &self.state.graph[node_id];
+            if !node
+                .user_data
+                .can_delete(node_id, &self.state.graph, &mut self.user_state)
+            {
+                info!("{:} can not be deleted", node.label);
+                continue;
+            }
+
+            let (node, disconnect_events) = self.remove_node(node_id);
+            // Pass the disconnection responses first so user code can perform cleanup
+            // before node removal response.
+            responses.extend(
+                disconnect_events
+                    .into_iter()
+                    .map(|(input, output)| NodeResponse::DisconnectEvent { output, input }),
+            );
+            responses.push(NodeResponse::DeleteNodeFull { node_id, node });
+        }
+        responses
+    }
+
+    fn is_connected_output(&self, output_id: OutputId) -> bool {
+        self.state
+            .graph
+            .connections
+            .iter()
+            .any(|(_, connected_output_id)| *connected_output_id == output_id)
+    }
+
+    /// Returns every node whose outputs are not consumed by another node, in the order their
+    /// statements are generated: the order of their sequence numbers, with the explicit `output`
+    /// nodes last since they form the result of the function.
+    pub fn find_root_nodes(&self) -> Vec<NodeId> {
+        let graph = &self.state.graph;
+        let mut roots: Vec<NodeId> = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| {
+                !node
+                    .outputs
+                    .iter()
+                    .any(|(_, output_id)| self.is_connected_output(*output_id))
+            })
+            .map(|(node_id, _)| node_id)
+            .collect();
+
+        roots.sort_by_key(|node_id| {
+            let node = &graph[*node_id];
+            (
+                node.user_data.template.label == "output",
+                node.user_data.sequence,
+                *node_id,
+            )
+        });
+        roots
+    }
+
+    /// Renumbers the nodes so `node_ids` come right before the nodes numbered `before` or
+    /// higher, or after every other node without `before`. `node_ids` keep their order among
+    /// each other. Used wherever nodes are added to the graph, so their statements are
+    /// generated where they belong.
+    pub fn place_in_sequence(&mut self, node_ids: &[NodeId], before: Option<u64>) {
+        let graph = &mut self.state.graph;
+        let mut placed: Vec<NodeId> = node_ids.to_vec();
+        placed.sort_by_key(|node_id| (graph[*node_id].user_data.sequence, *node_id));
+        let mut others: Vec<NodeId> = graph
+            .iter_nodes()
+            .filter(|node_id| !node_ids.contains(node_id))
+            .collect();
+        others.sort_by_key(|node_id| (graph[*node_id].user_data.sequence, *node_id));
+        let split = match before {
+            Some(before) => others
+                .iter()
+                .position(|node_id| graph[*node_id].user_data.sequence >= before)
+                .unwrap_or(others.len()),
+            None => others.len(),
+        };
+        let later = others.split_off(split);
+        for (sequence, node_id) in others
+            .iter()
+            .chain(placed.iter())
+            .chain(later.iter())
+            .enumerate()
+        {
+            if let Some(node) = graph.nodes.get_mut(*node_id) {
+                node.user_data.sequence = sequence as u64;
+            }
+        }
+    }
+
+    /// Roots that neither produce the result, nor have a side effect, nor compute anything from
+    /// the rest of the graph. They are left out of the generated code.
+    pub fn find_orphan_nodes(&self) -> Vec<NodeId> {
+        let graph = &self.state.graph;
+        let roots = self.find_root_nodes();
+        let has_output_node = roots
+            .iter()
+            .any(|root| graph[*root].user_data.template.label == "output");
+        let last_index = roots.len().saturating_sub(1);
+
+        roots
+            .into_iter()
+            .enumerate()
+            .filter(|(index, root)| {
+                let node = &graph[*root];
+                let is_output = node.user_data.template.label == "output";
+                let is_sink = node.user_data.template.outputs.is_empty();
+                // bare calls like `sleep 1` are statements of their own, even when every
+                // argument is an inline constant or there are no arguments at all
+                let is_call = matches!(
+                    node.user_data.template.call_style(),
+                    Some(WzrdCallStyle::Kernel)
+                );
+                let has_connected_input = node
+                    .inputs
+                    .iter()
+                    .any(|(_, input_id)| graph.connection(*input_id).is_some());
+                // without an explicit output node the last statement is the implicit result
+                let is_implicit_result = !has_output_node && *index == last_index;
+                !is_output && !is_sink && !is_call && !has_connected_input && !is_implicit_result
+            })
+            .map(|(_, root)| root)
+            .collect()
+    }
+
+    pub fn initialize_graph(
+        &mut self,
+        graph: &mut Graph<WzrdNodeData, WzrdNodeDataType, WzrdValueType>,
+        user_state: &mut WzrdGraphState,
+        code: &str,
+    ) {
+        let parser = Parser::new(code, ParserOptions::default());
+        let ParserResult { ast, comments, .. } = parser.do_parse();
+
+        // methods extracted before are part of the code now
+        self.extracted_methods.clear();
+        self.document = None;
+        self.parsed_code = code.to_string();
+        self.unsupported_code.clear();
+        if let Some(node) = ast {
+            debug!("whole ast {node:?}");
+            self.index_definitions(user_state, None, code, node.deref());
+            let parsed_statements = self.parse_document(code, node.deref(), &comments);
+            debug!("Parsed graph {parsed_statements:?}");
+            if let Some(function) = self.function_stack.back() {
+                // the method being edited is not a node of its own
+                let name = function.name.clone();
+                self.unregister_project_method(user_state, &name);
+            }
+            user_state.parameters = self
+                .function_stack
+                .back()
+                .map(|function| function.arguments.clone())
+                .unwrap_or_default();
+            for statement in parsed_statements.iter() {
+                self.build_graph(graph, user_state, statement);
+            }
+        }
+    }
+
+    /// Builds the statements of `code` into the graph and returns the new nodes, fanned out from
+    /// `position`. Only the body of a method is brought in, the method being edited stays the
+    /// same.
+    pub fn insert_code(&mut self, code: &str, position: Pos2) -> Vec<NodeId> {
+        let parser = Parser::new(code, ParserOptions::default());
+        let ParserResult { ast, .. } = parser.do_parse();
+        let node = match ast {
+            Some(node) => node,
+            None => {
+                debug!("the text is not ruby code");
+                return vec![];
+            }
+        };
+
+        let function_stack = self.function_stack.clone();
+        let unsupported_code = self.unsupported_code.clone();
+        self.parsed_code = code.to_string();
+        let parsed_statements = self.transform_statements(node.deref());
+        self.function_stack = function_stack;
+        // what can't be inserted is left out, the document itself is still complete
+        self.unsupported_code = unsupported_code;
+
+        let mut graph = std::mem::replace(&mut self.state.graph, WzrdGraph::new());
+        let mut user_state = std::mem::take(&mut self.user_state);
+        let existing: Vec<NodeId> = graph.iter_nodes().collect();
+        for statement in parsed_statements.iter() {
+            self.build_graph(&mut graph, &mut user_state, statement);
+        }
+        self.state.graph = graph;
+        self.user_state = user_state;
+
+        let inserted: Vec<NodeId> = self
+            .state
+            .graph
+            .iter_nodes()
+            .filter(|node_id| !existing.contains(node_id))
+            .collect();
+        // the parsed nodes all start at the same spot
+        for (index, node_id) in inserted.iter().enumerate() {
+            self.state
+                .node_positions
+                .insert(*node_id, position + Vec2::splat(20.0 * index as f32));
+            self.unplaced_nodes.remove(node_id);
+        }
+        inserted
+    }
+
+    fn build_graph(
+        &mut self,
+        graph: &mut Graph<WzrdNodeData, WzrdNodeDataType, WzrdValueType>,
+        user_state: &mut WzrdGraphState,
+        parsed_node: &ParsedWzrdNode,
+    ) -> Node<WzrdNodeData> {
+        let sequence = next_sequence(graph);
+        let new_node = graph.add_node(
+            parsed_node.wzrd_node.label.clone(),
+            parsed_node.wzrd_node.user_data(user_state),
+            |graph, node_id| parsed_node.wzrd_node.build_node(graph, user_state, node_id),
+        );
+        graph.nodes[new_node].user_data.sequence = sequence;
+
+        self.state.node_order.push(new_node);
+        self.state
+            .node_positions
+            .insert(new_node, Pos2 { x: 100.0, y: 100.0 });
+        self.unplaced_nodes.insert(new_node);
+
+        let current_node: Node<WzrdNodeData> = graph.nodes[new_node].clone();
+        for (i, (_, input_id)) in current_node.inputs.iter().enumerate() {
+            let parsed_input = match parsed_node.inputs.get(i) {
+                Some(Some(parsed_input)) => parsed_input,
+                _ => continue,
+            };
+            // literal arguments become the inline value of the socket instead of a node
+            if let Some(value) = parsed_input.folded_constant() {
+                let input = &mut graph.inputs[*input_id];
+                input.value = value;
+                input.kind = InputParamKind::ConnectionOrConstant;
+                continue;
+            }
+            let input_node = self.build_graph(graph, user_state, parsed_input);
+            if let Some((_, output_id)) = input_node.outputs.first() {
+                graph.add_connection(*output_id, *input_id);
+            }
+        }
+
+        current_node
+    }
+
+    /// The method `def` is, its parameters are kept as they were written.
+    fn parse_function(&self, def: &Def) -> WzrdFunction {
+        let args = def.args.as_deref();
+        WzrdFunction {
+            name: def.name.to_string(),
+            arguments: args
+                .map(|args| {
+                    parse_parameters(args)
+                        .into_iter()
+                        .map(|parameter| parameter.name)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            signature: args.and_then(|args| parameters_source(&self.parsed_code, args)),
+        }
+    }
+
+    /// Splits a method body into its statements, each of them becomes a root in the graph.
+    fn transform_statements(&mut self, node: &RNode) -> Vec<ParsedWzrdNode> {
+        match node {
+            RNode::Begin(begin) => begin
+                .statements
+                .iter()
+                .flat_map(|statement| self.transform_statements(statement))
+                .collect(),
+            RNode::Def(def) => {
+                let function = self.parse_function(def);
+                self.function_stack.push_back(function);
+                if let Some(body) = &def.body {
+                    self.transform_statements(body.deref())
+                } else {
+                    vec![]
+                }
+            }
+            _ => self.transform_ast(node).into_iter().collect(),
+        }
+    }
+
+    fn transform_assignment(
+        &mut self,
+        name: &str,
+        value: &Option<Box<RNode>>,
+    ) -> Option<ParsedWzrdNode> {
+        let value = self.transform_ast(value.as_ref()?.deref())?;
+
+        let mut template = WzrdNodes::Assignment.node();
+        if let Some(target) = template.inputs.get_mut(1) {
+            target.data_type = WzrdValueType::Expression { value: name.into() };
+        }
+
+        Some(ParsedWzrdNode {
+            wzrd_node: template,
+            inputs: vec![Some(value)],
+            value: None,
+        })
+    }
+
+    fn transform_operator(
+        &mut self,
+        operator: WzrdNodes,
+        lhs: &RNode,
+        rhs: &RNode,
+    ) -> Option<ParsedWzrdNode> {
+        let inputs = vec![self.transform_ast(lhs), self.transform_ast(rhs)];
+
+        Some(ParsedWzrdNode {
+            wzrd_node: operator.node(),
+            inputs,
+            value: None,
+        })
+    }
+
+    fn transform_if(
+        &mut self,
+        cond: &RNode,
+        if_true: Option<&RNode>,
+        if_false: Option<&RNode>,
+    ) -> Option<ParsedWzrdNode> {
+        let condition = self.transform_ast(cond)?;
+        let then_branch = if_true
+            .and_then(|node| self.transform_ast(node))
+            .map(Self::with_output);
+        let else_branch = if_false
+            .and_then(|node| self.transform_ast(node))
+            .map(Self::with_output);
+
+        Some(ParsedWzrdNode {
+            wzrd_node: WzrdNodes::If.node(),
+            inputs: vec![Some(condition), then_branch, else_branch],
+            value: None,
+        })
+    }
+
+    /// Statements like `puts` have no value, they get an output so they can still be connected
+    /// where a value is expected, like in a branch.
+    fn with_output(mut statement: ParsedWzrdNode) -> ParsedWzrdNode {
+        if statement.wzrd_node.outputs.is_empty() {
+            statement.wzrd_node.outputs = vec![WzrdType {
+                name: "out".into(),
+                data_type: WzrdValueType::Any,
+                order: 1,
+            }];
+        }
+        statement
+    }
+
+    /// Several statements used as one value, like the body of a branch. A Block node takes
+    /// each of them in order.
+    fn transform_block(&self, mut statements: Vec<ParsedWzrdNode>) -> Option<ParsedWzrdNode> {
+        if statements.len() <= 1 {
+            return statements.pop();
+        }
+
+        let mut template = WzrdNodes::Block.node();
+        template.inputs = (1..=statements.len())
+            .map(|order| WzrdType {
+                name: format!("statement {order}"),
+                data_type: WzrdValueType::Any,
+                order: order as u8,
+            })
+            .collect();
+
+        Some(ParsedWzrdNode {
+            wzrd_node: template,
+            inputs: statements
+                .into_iter()
+                .map(|statement| Some(Self::with_output(statement)))
+                .collect(),
+            value: None,
+        })
+    }
+
+    /// A Constant node holding `value` in its input.
+    /// An argument of a call, or the source of the argument as an expression if the graph has
+    /// no node for it.
+    fn transform_argument(&mut self, node: &RNode) -> ParsedWzrdNode {
+        let unsupported = self.unsupported_code.len();
+        self.transform_ast(node).unwrap_or_else(|| {
+            // the argument is kept as a whole, nothing inside it gets lost
+            self.unsupported_code.truncate(unsupported);
+            let value = self.source_of(node);
+            self.transform_constant(WzrdValueType::Expression { value }, None)
+        })
+    }
+
+    fn source_of(&self, node: &RNode) -> String {
+        let location = node.expression();
+        self.parsed_code
+            .get(location.begin..location.end)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Notes `node` in [`WzrdNodeGraph::unsupported_code`], the graph has no node for it.
+    fn unsupported(&mut self, node: &RNode) -> Option<ParsedWzrdNode> {
+        debug!("no node for {node:?}");
+        self.unsupported_code.push(self.source_of(node));
+        None
+    }
+
+    fn transform_constant(
+        &self,
+        value: WzrdValueType,
+        parsed: Option<ParsedValueType>,
+    ) -> ParsedWzrdNode {
+        let mut template = WzrdNodes::Constant.node();
+        //is it bad to assume a constant has one input?
+        if let Some(input) = template.inputs.first() {
+            let mut cloned = input.clone();
+            cloned.data_type = value;
+            template.inputs = vec![cloned];
+        }
+        ParsedWzrdNode {
+            wzrd_node: template,
+            inputs: vec![],
+            value: parsed,
+        }
+    }
+
+    /// Only ranges between two integer literals can be edited as a constant.
+    fn transform_range(
+        &mut self,
+        start: Option<&RNode>,
+        end: Option<&RNode>,
+        exclusive: bool,
+    ) -> Option<ParsedWzrdNode> {
+        let bound = |node: Option<&RNode>| match node {
+            Some(RNode::Int(int)) => integer_literal(&int.value),
+            _ => None,
+        };
+        if let (Some(start), Some(end)) = (bound(start), bound(end)) {
+            return Some(self.transform_constant(
+                WzrdValueType::Range {
+                    start,
+                    end,
+                    exclusive,
+                },
+                None,
+            ));
+        }
+        // other bounds are connected to a range node, which keeps them as they were written
+        let mut inputs = vec![];
+        for node in [start, end].into_iter().flatten() {
+            inputs.push(Some(self.transform_ast(node)?));
+        }
+        Some(ParsedWzrdNode {
+            wzrd_node: WzrdNode::range(exclusive, start.is_some(), end.is_some()),
+            value: None,
+            inputs,
+        })
+    }
+
+    /// Integers too large for a constant or written in a way [`integer_literal`] can't read are
+    /// kept as an expression rather than changed.
+    fn transform_integer(&self, source: &str) -> ParsedWzrdNode {
+        match integer_literal(source) {
+            Some(value) => self.transform_constant(
+                WzrdValueType::Integer { value },
+                Some(ParsedValueType::Int(value as i128)),
+            ),
+            None => {
+                warn!("can't read the integer {source}, keeping it as an expression");
+                self.transform_constant(
+                    WzrdValueType::Expression {
+                        value: source.into(),
+                    },
+                    None,
+                )
+            }
+        }
+    }
+
+    fn transform_ast(&mut self, node: &RNode) -> Option<ParsedWzrdNode> {
+        match node {
+            RNode::Begin(begin) => {
+                debug!("{{");
+                let statements: Vec<ParsedWzrdNode> = begin
+                    .statements
+                    .iter()
+                    .filter_map(|node| self.transform_ast(node))
+                    .collect();
+                debug!("}}");
+
+                self.transform_block(statements)
+            }
+            RNode::Send(send) => {
+                // calls on `self` are treated like receiver-less calls to project methods
+                let recv = send
+                    .recv
+                    .as_deref()
+                    .filter(|recv| !matches!(recv, RNode::Self_(_)));
+                let mut inputs: Vec<Option<ParsedWzrdNode>> = vec![];
+                if let Some(recv) = recv {
+                    let receiver = self.transform_argument(recv);
+                    debug!("{:?} {:?}", receiver.value, send.method_name);
+                    inputs.push(Some(receiver));
+                }
+                // keyword arguments, hashes, arrays and interpolations keep their source
+                inputs.extend(
+                    send.args
+                        .iter()
+                        .map(|arg| Some(self.transform_argument(arg))),
+                );
+
+                let style = match recv {
+                    Some(_) => WzrdCallStyle::Receiver,
+                    None => WzrdCallStyle::Kernel,
+                };
+                Some(ParsedWzrdNode {
+                    wzrd_node: self.find_call_template(&send.method_name, style, inputs.len()),
+                    value: None,
+                    inputs,
+                })
+            }
+            RNode::And(and) => self.transform_operator(WzrdNodes::And, &and.lhs, &and.rhs),
+            RNode::Or(or) => self.transform_operator(WzrdNodes::Or, &or.lhs, &or.rhs),
+            RNode::Lvasgn(lvasgn) => self.transform_assignment(&lvasgn.name, &lvasgn.value),
+            RNode::Ivasgn(ivasgn) => self.transform_assignment(&ivasgn.name, &ivasgn.value),
+            RNode::Ivar(ivar) => {
+                let mut template = WzrdNodes::Variable.node();
+                template.outputs = vec![WzrdType {
+                    name: ivar.name.to_string(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }];
+
+                Some(ParsedWzrdNode {
+                    wzrd_node: template,
+                    inputs: vec![],
+                    value: None,
+                })
+            }
+            RNode::Int(int) => Some(self.transform_integer(&int.value)),
+            RNode::Float(float) => Some(self.transform_constant(
+                WzrdValueType::Float {
+                    value: float.value.parse().unwrap_or(0.0),
+                },
+                None,
+            )),
+            RNode::Str(string) => {
+                let value = string.value.to_string_lossy();
+                Some(self.transform_constant(
+                    WzrdValueType::String {
+                        value: value.clone(),
+                    },
+                    Some(ParsedValueType::String(value)),
+                ))
+            }
+            RNode::True(_) => {
+                Some(self.transform_constant(WzrdValueType::Boolean { value: true }, None))
+            }
+            RNode::False(_) => {
+                Some(self.transform_constant(WzrdValueType::Boolean { value: false }, None))
+            }
+            RNode::Nil(_) => Some(self.transform_constant(WzrdValueType::Nil, None)),
+            RNode::Sym(sym) => Some(self.transform_constant(
+                WzrdValueType::Symbol {
+                    value: sym.name.to_string_lossy(),
+                },
+                None,
+            )),
+            RNode::Irange(range) => {
+                self.transform_range(range.left.as_deref(), range.right.as_deref(), false)
+            }
+            RNode::Erange(range) => {
+                self.transform_range(range.left.as_deref(), range.right.as_deref(), true)
+            }
+            RNode::Regexp(regexp) => {
+                // interpolated patterns can't be edited as a literal
+                let mut value = String::new();
+                for part in regexp.parts.iter() {
+                    match part {
+                        RNode::Str(string) => value.push_str(&string.value.to_string_lossy()),
+                        _ => return self.unsupported(node),
+                    }
+                }
+                let options = match regexp.options.as_deref() {
+                    Some(RNode::RegOpt(reg_opt)) => reg_opt.options.clone().unwrap_or_default(),
+                    _ => String::new(),
+                };
+                Some(self.transform_constant(WzrdValueType::Regexp { value, options }, None))
+            }
+            RNode::Def(def) => {
+                let function = self.parse_function(def);
+                self.function_stack.push_back(function);
+                if let Some(body) = &def.body {
+                    self.transform_ast(body.deref())
+                } else {
+                    None
+                }
+            }
+            RNode::Lvar(lvar) => {
+                let mut template = WzrdNodes::Variable.node();
+                template.outputs = vec![WzrdType {
+                    name: lvar.name.to_string(),
+                    data_type: WzrdValueType::Any,
+                    order: 1,
+                }];
+
+                Some(ParsedWzrdNode {
+                    wzrd_node: template,
+                    inputs: vec![],
+                    value: None,
+                })
+            }
+            RNode::Return(ret) => {
+                let argument = ret.args.first().and_then(|node| self.transform_ast(node));
+
+                Some(ParsedWzrdNode {
+                    wzrd_node: WzrdNodes::Output.node(),
+                    inputs: vec![argument],
+                    value: None,
+                })
+            }
+            RNode::If(if_node) => self.transform_if(
+                &if_node.cond,
+                if_node.if_true.as_deref(),
+                if_node.if_false.as_deref(),
+            ),
+            RNode::IfMod(if_mod) => self.transform_if(
+                &if_mod.cond,
+                if_mod.if_true.as_deref(),
+                if_mod.if_false.as_deref(),
+            ),
+            RNode::IfTernary(ternary) => self.transform_if(
+                &ternary.cond,
+                Some(&ternary.if_true),
+                Some(&ternary.if_false),
+            ),
+            _ => self.unsupported(node),
+        }
+    }
+
+    /// Lays the whole graph out in layers, see [`layout::layered_layout`]. Nodes moved by hand
+    /// are laid out as well and no longer pinned.
+    pub fn format_graph(&mut self) {
+        let new_positions = self.layout_positions();
+        self.pinned_nodes.clear();
+        self.unplaced_nodes.clear();
+        self.state.node_positions = new_positions;
+    }
+}
+
+/// The sequence number of a node added after every node of `graph`. Statements are parsed
+/// before their inputs, so numbering nodes as they are built keeps the order of the code.
+fn next_sequence(graph: &WzrdGraph) -> u64 {
+    graph
+        .nodes
+        .iter()
+        .map(|(_, node)| node.user_data.sequence + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether the connections between `nodes` nodes, given as the indices of the node giving the
+/// value and the node taking it, go round in a circle. Nodes left over once every node without
+/// a source has been taken away lie on one.
+fn connections_loop(nodes: usize, connections: &[(usize, usize)]) -> bool {
+    let mut sources = vec![0; nodes];
+    for (_, consumer) in connections.iter() {
+        sources[*consumer] += 1;
+    }
+    let mut free: Vec<usize> = (0..nodes).filter(|node| sources[*node] == 0).collect();
+    let mut removed = 0;
+    while let Some(node) = free.pop() {
+        removed += 1;
+        for (_, consumer) in connections.iter().filter(|(source, _)| *source == node) {
+            sources[*consumer] -= 1;
+            if sources[*consumer] == 0 {
+                free.push(*consumer);
+            }
+        }
+    }
+    removed < nodes
+}
+
+/// FNV-1a hash of `text`. Unlike the hasher of the standard library it does not change between
+/// builds, so it can be part of keys that get saved.
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A double quoted Ruby string, `#` is escaped where it would start an interpolation.
+fn string_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '#' if matches!(chars.peek(), Some('{' | '@' | '$')) => literal.push_str("\\#"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Value of a Ruby integer literal, with its sign, `_` separators and `0x`, `0b`, `0o`, `0d`
+/// or a leading `0` for the radix. `None` if it doesn't fit an `i64`.
+fn integer_literal(source: &str) -> Option<i64> {
+    let digits = source.replace('_', "");
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits.trim_start_matches('+')),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let (radix, digits) = match lower.as_bytes() {
+        [b'0', b'x', ..] => (16, &digits[2..]),
+        [b'0', b'b', ..] => (2, &digits[2..]),
+        [b'0', b'o', ..] => (8, &digits[2..]),
+        [b'0', b'd', ..] => (10, &digits[2..]),
+        [b'0', _, ..] => (8, &digits[1..]),
+        _ => (10, digits),
+    };
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let magnitude = i128::from_str_radix(digits, radix).ok()?;
+    i64::try_from(if negative { -magnitude } else { magnitude }).ok()
+}
+
+/// The pattern between the slashes of a Regexp literal, slashes not already escaped are.
+fn regexp_source(pattern: &str) -> String {
+    let mut source = String::new();
+    let mut escaped = false;
+    for c in pattern.chars() {
+        if c == '/' && !escaped {
+            source.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        source.push(c);
+    }
+    source
+}
+
+/// Floats always keep their decimal point, otherwise Ruby reads them back as integers.
+fn float_literal(value: f64) -> String {
+    if value.is_nan() {
+        "Float::NAN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            "Float::INFINITY".into()
+        } else {
+            "-Float::INFINITY".into()
+        }
+    } else {
+        format!("{value:?}")
+    }
+}
+
+impl WzrdValueType {
+    /// Ruby source of the value, Any has no value and results in an empty string.
+    pub fn to_literal(&self) -> String {
+        match self {
+            WzrdValueType::String { value } => string_literal(value),
+            WzrdValueType::Expression { value } => value.clone(),
+            WzrdValueType::Integer { value } => format!("{value}"),
+            WzrdValueType::Number {
+                value,
+                decimal: true,
+            }
+            | WzrdValueType::Float { value } => float_literal(*value),
+            WzrdValueType::Number {
+                value,
+                decimal: false,
+            } => format!("{:}", value.round() as i64),
+            WzrdValueType::Boolean { value } => format!("{value}"),
+            WzrdValueType::Nil => "nil".into(),
+            WzrdValueType::Symbol { value } => symbol_literal(value),
+            WzrdValueType::Range {
+                start,
+                end,
+                exclusive,
+            } => format!("({start}{:}{end})", if *exclusive { "..." } else { ".." }),
+            WzrdValueType::Regexp { value, options } => {
+                format!("/{:}/{options}", regexp_source(value))
+            }
+            WzrdValueType::Any => "".into(),
+        }
+    }
+}
+
+/// `:name` for names Ruby accepts bare, `:"name"` for everything else.
+fn symbol_literal(name: &str) -> String {
+    lazy_static! {
+        static ref BARE_SYMBOL_REGEX: Regex =
+            Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*[?!=]?$").unwrap();
+    }
+    if BARE_SYMBOL_REGEX.is_match(name) {
+        format!(":{name}")
+    } else {
+        format!(":{:}", string_literal(name))
+    }
+}
+
+impl From<WzrdValueType> for WzrdNodeDataType {
+    fn from(value: WzrdValueType) -> Self {
+        match value {
+            WzrdValueType::String { .. } => WzrdNodeDataType::String,
+            WzrdValueType::Expression { .. } => WzrdNodeDataType::Expression,
+            WzrdValueType::Integer { .. } => WzrdNodeDataType::Integer,
+            WzrdValueType::Float { .. } => WzrdNodeDataType::Float,
+            WzrdValueType::Number { .. } => WzrdNodeDataType::Number,
+            WzrdValueType::Boolean { .. } => WzrdNodeDataType::Boolean,
+            WzrdValueType::Nil => WzrdNodeDataType::Nil,
+            WzrdValueType::Symbol { .. } => WzrdNodeDataType::Symbol,
+            WzrdValueType::Range { .. } => WzrdNodeDataType::Range,
+            WzrdValueType::Regexp { .. } => WzrdNodeDataType::Regexp,
+            WzrdValueType::Any => WzrdNodeDataType::Any,
+            // WzrdValueType::None => WzrdNodeDataType::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_node(graph: &mut WzrdNodeGraph, template: WzrdNode) -> NodeId {
+        let node_id = graph.state.graph.add_node(
+            template.label.clone(),
+            template.user_data(&mut graph.user_state),
+            |inner, node_id| template.build_node(inner, &mut WzrdGraphState::default(), node_id),
+        );
+        graph.place_in_sequence(&[node_id], None);
+        node_id
+    }
+
+    fn variable(graph: &mut WzrdNodeGraph, name: &str) -> NodeId {
+        let mut template = WzrdNodes::Variable.node();
+        template.outputs = vec![WzrdType {
+            name: name.into(),
+            data_type: WzrdValueType::Any,
+            order: 1,
+        }];
+        add_node(graph, template)
+    }
+
+    fn connect(graph: &mut WzrdNodeGraph, from: NodeId, to: NodeId, input: usize) {
+        let output_id = graph.state.graph[from].outputs[0].1;
+        let input_id = graph.state.graph[to].inputs[input].1;
+        graph.state.graph.add_connection(output_id, input_id);
+    }
+
+    fn generate(graph: &mut WzrdNodeGraph) -> String {
+        graph.evaluate_graph(&mut NodeCache::new()).unwrap()
+    }
+
+    #[test]
+    fn shared_value_of_one_branch_is_computed_inside_it() {
+        let mut graph = WzrdNodeGraph::new();
+        let condition = variable(&mut graph, "c");
+        let list = variable(&mut graph, "list");
+        let conditional = add_node(&mut graph, WzrdNodes::If.node());
+        let size = add_node(&mut graph, WzrdNodes::Size.node());
+        let sum = add_node(&mut graph, WzrdNodes::Add.node());
+        connect(&mut graph, condition, conditional, 0);
+        connect(&mut graph, list, size, 0);
+        connect(&mut graph, size, sum, 0);
+        connect(&mut graph, size, sum, 1);
+        connect(&mut graph, sum, conditional, 1);
+        connect(&mut graph, list, conditional, 2);
+
+        assert_eq!(
+            generate(&mut graph),
+            "if c\n    tmp = list.size\n    (tmp+tmp)\nelse\n    list\nend"
+        );
+    }
+
+    #[test]
+    fn shared_value_used_outside_the_branch_is_computed_before_it() {
+        let mut graph = WzrdNodeGraph::new();
+        let list = variable(&mut graph, "list");
+        let size = add_node(&mut graph, WzrdNodes::Size.node());
+        let conditional = add_node(&mut graph, WzrdNodes::If.node());
+        connect(&mut graph, list, size, 0);
+        connect(&mut graph, size, conditional, 0);
+        connect(&mut graph, size, conditional, 1);
+        connect(&mut graph, list, conditional, 2);
+
+        assert_eq!(generate(&mut graph), "tmp = list.size\n(tmp) ? tmp : list");
+    }
+
+    #[test]
+    fn integer_literals_are_read_in_their_radix() {
+        assert_eq!(integer_literal("1_000"), Some(1000));
+        assert_eq!(integer_literal("-0x2A"), Some(-42));
+        assert_eq!(integer_literal("0b101"), Some(5));
+        assert_eq!(integer_literal("0o17"), Some(15));
+        assert_eq!(integer_literal("017"), Some(15));
+        assert_eq!(integer_literal("0d19"), Some(19));
+        assert_eq!(integer_literal("0"), Some(0));
+        assert_eq!(integer_literal("123456789012345678901234567890"), None);
+    }
+
+    #[test]
+    fn slashes_of_regexps_are_escaped_once() {
+        let regexp = |value: &str| WzrdValueType::Regexp {
+            value: value.into(),
+            options: "i".into(),
+        };
+        assert_eq!(regexp("wz/rd").to_literal(), r"/wz\/rd/i");
+        assert_eq!(regexp(r"wz\/rd").to_literal(), r"/wz\/rd/i");
+        assert_eq!(regexp(r"wz\\/rd").to_literal(), r"/wz\\\/rd/i");
+    }
+
+    #[test]
+    fn only_an_orphaned_last_parameter_can_be_deleted() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.user_state.parameters = vec!["list".into()];
+        let list = variable(&mut graph, "list");
+        let size = add_node(&mut graph, WzrdNodes::Size.node());
+        connect(&mut graph, list, size, 0);
+        graph.pinned_nodes.insert(list);
+
+        graph.delete_nodes(&[list]);
+        assert!(graph.state.graph.nodes.contains_key(list));
+
+        graph.delete_nodes(&[size]);
+        graph.delete_nodes(&[list]);
+        assert!(!graph.state.graph.nodes.contains_key(list));
+        assert!(graph.pinned_nodes.is_empty());
+    }
+
+    #[test]
+    fn only_sockets_with_a_value_type_take_a_constant() {
+        let mut graph = WzrdNodeGraph::new();
+        let difference = add_node(&mut graph, WzrdNodes::Subtract.node());
+        let puts = add_node(&mut graph, WzrdNodes::Puts.node());
+        let kind = |node_id: NodeId| {
+            graph
+                .state
+                .graph
+                .get_input(graph.state.graph[node_id].inputs[0].1)
+                .kind
+        };
+
+        assert!(matches!(
+            kind(difference),
+            InputParamKind::ConnectionOrConstant
+        ));
+        assert!(matches!(kind(puts), InputParamKind::ConnectionOnly));
+    }
+
+    #[test]
+    fn literal_arguments_are_folded_into_their_socket() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1.abs + 2\n", None);
+        let state = &graph.state;
+        assert!(state
+            .graph
+            .iter_nodes()
+            .all(|node_id| state.graph[node_id].label != "Constant"));
+
+        let sum = state
+            .graph
+            .iter_nodes()
+            .find(|node_id| state.graph[*node_id].label == "+")
+            .unwrap();
+        let folded = state.graph.get_input(state.graph[sum].inputs[1].1);
+        assert_eq!(folded.value.to_literal(), "2");
+        assert!(matches!(folded.kind, InputParamKind::ConnectionOrConstant));
+    }
+
+    #[test]
+    fn inline_constants_are_generated_unless_a_node_is_connected() {
+        let mut graph = WzrdNodeGraph::new();
+        let a = variable(&mut graph, "a");
+        let b = variable(&mut graph, "b");
+        let sum = add_node(&mut graph, WzrdNodes::Add.node());
+        connect(&mut graph, a, sum, 0);
+        let input_id = graph.state.graph[sum].inputs[1].1;
+        graph.state.graph.inputs[input_id].value = WzrdValueType::Number {
+            value: 5.0,
+            decimal: false,
+        };
+        assert_eq!(generate(&mut graph), "(a+5)");
+
+        connect(&mut graph, b, sum, 1);
+        assert_eq!(generate(&mut graph), "(a+b)");
+    }
+
+    #[test]
+    fn every_value_generates_its_literal() {
+        let number = |value: f64, decimal: bool| WzrdValueType::Number { value, decimal };
+        let cases = [
+            (number(2.0, false), "2"),
+            (number(2.0, true), "2.0"),
+            (number(2.5, true), "2.5"),
+            (WzrdValueType::Integer { value: -3 }, "-3"),
+            (WzrdValueType::Float { value: 3.0 }, "3.0"),
+            (WzrdValueType::Float { value: f64::NAN }, "Float::NAN"),
+            (
+                WzrdValueType::Float {
+                    value: f64::NEG_INFINITY,
+                },
+                "-Float::INFINITY",
+            ),
+            (
+                WzrdValueType::String {
+                    value: "say \"#{hi}\"\n".into(),
+                },
+                r#""say \"\#{hi}\"\n""#,
+            ),
+            (
+                WzrdValueType::Expression {
+                    value: "a.b".into(),
+                },
+                "a.b",
+            ),
+            (WzrdValueType::Boolean { value: true }, "true"),
+            (WzrdValueType::Nil, "nil"),
+            (
+                WzrdValueType::Symbol {
+                    value: "name?".into(),
+                },
+                ":name?",
+            ),
+            (
+                WzrdValueType::Symbol {
+                    value: "two words".into(),
+                },
+                r#":"two words""#,
+            ),
+            (
+                WzrdValueType::Range {
+                    start: 1,
+                    end: 3,
+                    exclusive: true,
+                },
+                "(1...3)",
+            ),
+            (WzrdValueType::Any, ""),
+        ];
+        for (value, literal) in cases {
+            assert_eq!(value.to_literal(), literal, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn graphs_that_loop_back_are_not_generated() {
+        let mut graph = WzrdNodeGraph::new();
+        let first = add_node(&mut graph, WzrdNodes::Add.node());
+        let second = add_node(&mut graph, WzrdNodes::Add.node());
+        let puts = add_node(&mut graph, WzrdNodes::Puts.node());
+        connect(&mut graph, first, second, 0);
+        connect(&mut graph, second, first, 0);
+        connect(&mut graph, second, puts, 0);
+
+        assert!(graph.evaluate_graph(&mut NodeCache::new()).is_err());
+    }
+
+    #[test]
+    fn documents_with_code_the_graph_can_not_show_are_not_written() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("# frozen_string_literal: true\nputs 1\n", None);
+        assert_eq!(
+            generate(&mut graph),
+            "# frozen_string_literal: true\nputs 1"
+        );
+
+        for code in [
+            "puts 1\n# the answer\nputs 42\n",
+            "x = 1\nwhile x < 3\n  x = x + 1\nend\n",
+            "class A\n  def main\n    # nothing yet\n  end\nend\n",
+        ] {
+            graph.load_document(code, None);
+            assert!(
+                graph.evaluate_graph(&mut NodeCache::new()).is_err(),
+                "{code}"
+            );
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::graph_core::wzrd_node_graph::{ParsedWzrdNode, RNode, WzrdNodeGraph, LAYOUT_COMMENT};
+use lib_ruby_parser::source::Comment;
+use std::ops::Range;
+
+/// The code of a document that is not part of the graph. Only one method of a document is
+/// edited as a graph, everything around it is kept as it was written and put back around the
+/// generated method.
+#[derive(Clone, Debug)]
+pub enum WzrdDocument {
+    /// A document of methods, like a class. `indent` is the indentation of the edited method.
+    Method {
+        before: String,
+        after: String,
+        indent: String,
+    },
+    /// A script, its top-level statements are edited. Its definitions are kept in front of
+    /// them, so they are defined before the statements call them. `header` holds the comments
+    /// in front of the first statement, like a shebang or magic comments.
+    Script {
+        header: String,
+        definitions: Vec<String>,
+    },
+}
+
+fn is_definition(node: &RNode) -> bool {
+    matches!(node, RNode::Def(_) | RNode::Class(_) | RNode::Module(_))
+}
+
+fn top_level_statements(node: &RNode) -> Vec<&RNode> {
+    match node {
+        RNode::Begin(begin) => begin.statements.iter().collect(),
+        node => vec![node],
+    }
+}
+
+fn collect_methods<'a>(node: &'a RNode, methods: &mut Vec<&'a RNode>) {
+    let body = match node {
+        RNode::Begin(begin) => {
+            for statement in begin.statements.iter() {
+                collect_methods(statement, methods);
+            }
+            return;
+        }
+        RNode::Def(_) => {
+            methods.push(node);
+            return;
+        }
+        RNode::Class(class) => &class.body,
+        RNode::Module(module) => &module.body,
+        _ => return,
+    };
+    if let Some(body) = body {
+        collect_methods(body, methods);
+    }
+}
+
+/// The method called `name`, or the first method of the document.
+fn find_method<'a>(node: &'a RNode, name: Option<&str>) -> Option<&'a RNode> {
+    let mut methods = vec![];
+    collect_methods(node, &mut methods);
+    methods
+        .iter()
+        .find(|method| matches!(method, RNode::Def(def) if Some(def.name.as_str()) == name))
+        .or(methods.first())
+        .copied()
+}
+
+fn without_layout_comment(code: &str) -> String {
+    code.split_inclusive('\n')
+        .filter(|line| !line.trim().starts_with(LAYOUT_COMMENT.trim_end()))
+        .collect()
+}
+
+/// Indents every line but the first, which continues the line it is put in.
+fn indent_continued(code: &str, indent: &str) -> String {
+    code.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            if index == 0 || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl WzrdDocument {
+    /// Puts the generated `code` of the graph back into the document, together with the
+    /// `methods` extracted from it.
+    pub fn assemble(&self, code: &str, methods: &[&str]) -> String {
+        match self {
+            WzrdDocument::Method {
+                before,
+                after,
+                indent,
+            } => {
+                let mut document = before.clone();
+                document.push_str(&indent_continued(code.trim_end(), indent));
+                for method in methods {
+                    document.push_str("\n\n");
+                    document.push_str(indent);
+                    document.push_str(&indent_continued(method.trim_end(), indent));
+                }
+                document.push_str(after);
+                document
+            }
+            WzrdDocument::Script {
+                header,
+                definitions,
+            } => {
+                let mut parts: Vec<&str> = definitions.iter().map(String::as_str).collect();
+                parts.extend(methods.iter().map(|method| method.trim_end()));
+                parts.push(code);
+                format!("{header}{:}", parts.join("\n\n"))
+            }
+        }
+    }
+}
+
+impl WzrdNodeGraph {
+    /// Splits the parsed `code` into the statements the graph is built from and the code kept
+    /// around them, see [`WzrdDocument`]. A method that was edited before is edited again if
+    /// the document still has it. `comments` among the statements are noted as unsupported.
+    pub(super) fn parse_document(
+        &mut self,
+        code: &str,
+        node: &RNode,
+        comments: &[Comment],
+    ) -> Vec<ParsedWzrdNode> {
+        let statements = top_level_statements(node);
+        if statements.iter().any(|statement| !is_definition(statement)) {
+            let span = |statement: &&RNode| {
+                let location = statement.expression();
+                location.begin..location.end
+            };
+            let kept: Vec<Range<usize>> = statements
+                .iter()
+                .filter(|statement| is_definition(statement))
+                .map(span)
+                .collect();
+            let first = statements.first().map(span).unwrap_or_default().start;
+            let edited = first..code.len();
+            self.note_comments(code, comments, &edited, &kept);
+            self.document = Some(WzrdDocument::Script {
+                header: code[..first].to_string(),
+                definitions: kept
+                    .into_iter()
+                    .map(|span| code[span].to_string())
+                    .collect(),
+            });
+            self.function_stack.clear();
+            return statements
+                .into_iter()
+                .filter(|statement| !is_definition(statement))
+                .flat_map(|statement| self.transform_statements(statement))
+                .collect();
+        }
+
+        let edited = self
+            .function_stack
+            .back()
+            .map(|function| function.name.clone());
+        let method = match find_method(node, edited.as_deref()) {
+            Some(method) => method,
+            None => {
+                self.document = None;
+                return vec![];
+            }
+        };
+        let location = method.expression();
+        self.note_comments(code, comments, &(location.begin..location.end), &[]);
+        let before = code[..location.begin].to_string();
+        let indent = before
+            .rsplit('\n')
+            .next()
+            .filter(|line| line.trim().is_empty())
+            .unwrap_or_default()
+            .to_string();
+        self.document = Some(WzrdDocument::Method {
+            before,
+            after: without_layout_comment(&code[location.end..]),
+            indent,
+        });
+        self.function_stack.clear();
+        self.transform_statements(method)
+    }
+    /// Notes the comments of `code` within `edited` that are not in one of the `kept` spans,
+    /// the generated code would not have them. The layout comment is written again anyway.
+    fn note_comments(
+        &mut self,
+        code: &str,
+        comments: &[Comment],
+        edited: &Range<usize>,
+        kept: &[Range<usize>],
+    ) {
+        for comment in comments {
+            let location = &comment.location;
+            let text = &code[location.begin..location.end];
+            let is_kept = kept.iter().any(|span| span.contains(&location.begin));
+            if edited.contains(&location.begin)
+                && !is_kept
+                && !text.starts_with(LAYOUT_COMMENT.trim_end())
+            {
+                self.unsupported_code.push(text.trim_end().to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::graph_core::node::structs::{WzrdGraphState, WzrdNodeData, WzrdValueType};
+use crate::graph_core::wzrd_node_graph::{connections_loop, WzrdGraph, WzrdNodeGraph};
+use anyhow::bail;
+use egui_node_graph::{InputParamKind, NodeId, NodeTemplateTrait};
+use emath::{pos2, Pos2, Vec2};
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+use slotmap::SecondaryMap;
+
+/// A self-contained copy of some nodes and the connections between them. Nodes and their
+/// sockets are referenced by position, so a fragment can be inserted into any graph.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdGraphFragment {
+    pub nodes: Vec<WzrdFragmentNode>,
+    pub connections: Vec<WzrdFragmentConnection>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdFragmentNode {
+    pub label: String,
+    pub user_data: WzrdNodeData,
+    pub input_values: Vec<WzrdValueType>,
+    pub position: [f32; 2],
+}
+
+/// `(node index, socket index)` pairs of an output and the input it feeds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdFragmentConnection {
+    pub output: (usize, usize),
+    pub input: (usize, usize),
+}
+
+impl WzrdGraphFragment {
+    /// Copies `node_ids` out of `graph`, connections to nodes outside the selection are dropped.
+    pub fn extract(
+        graph: &WzrdGraph,
+        positions: &SecondaryMap<NodeId, Pos2>,
+        node_ids: &[NodeId],
+    ) -> Self {
+        let index_of = |node_id: NodeId| node_ids.iter().position(|id| *id == node_id);
+
+        let nodes = node_ids
+            .iter()
+            .map(|node_id| {
+                let node = &graph[*node_id];
+                let position = positions.get(*node_id).copied().unwrap_or(Pos2::ZERO);
+                WzrdFragmentNode {
+                    label: node.label.clone(),
+                    user_data: node.user_data.clone(),
+                    input_values: node
+                        .inputs
+                        .iter()
+                        .map(|(_, input_id)| graph.inputs[*input_id].value.clone())
+                        .collect(),
+                    position: [position.x, position.y],
+                }
+            })
+            .collect();
+
+        let mut connections = vec![];
+        for (input_node_index, node_id) in node_ids.iter().enumerate() {
+            for (input_index, (_, input_id)) in graph[*node_id].inputs.iter().enumerate() {
+                if let Some(output_id) = graph.connection(*input_id) {
+                    let output_node_id = graph.get_output(output_id).node;
+                    if let Some(output_node_index) = index_of(output_node_id) {
+                        let output_index = graph[output_node_id]
+                            .outputs
+                            .iter()
+                            .position(|(_, id)| *id == output_id)
+                            .unwrap_or(0);
+                        connections.push(WzrdFragmentConnection {
+                            output: (output_node_index, output_index),
+                            input: (input_node_index, input_index),
+                        });
+                    }
+                }
+            }
+        }
+
+        Self { nodes, connections }
+    }
+
+    pub fn center(&self) -> Pos2 {
+        if self.nodes.is_empty() {
+            return Pos2::ZERO;
+        }
+        let sum = self
+            .nodes
+            .iter()
+            .fold(Vec2::ZERO, |sum, node| sum + Vec2::from(node.position));
+        (sum / self.nodes.len() as f32).to_pos2()
+    }
+
+    /// Whether node `node_index` has an input `input_index`, or an output with `output`.
+    pub fn has_socket(&self, (node_index, socket_index): (usize, usize), output: bool) -> bool {
+        self.nodes.get(node_index).is_some_and(|node| {
+            let template = &node.user_data.template;
+            let sockets = if output {
+                &template.outputs
+            } else {
+                &template.inputs
+            };
+            socket_index < sockets.len()
+        })
+    }
+
+    /// Checks that the connections point at nodes and sockets of the fragment and do not go
+    /// round in a circle. Fragments read from the clipboard or a file may not.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for connection in self.connections.iter() {
+            if !self.has_socket(connection.output, true)
+                || !self.has_socket(connection.input, false)
+            {
+                bail!("the fragment connects sockets it does not have: {connection:?}");
+            }
+        }
+        let connections: Vec<(usize, usize)> = self
+            .connections
+            .iter()
+            .map(|connection| (connection.output.0, connection.input.0))
+            .collect();
+        if connections_loop(self.nodes.len(), &connections) {
+            bail!("the fragment connects its nodes in a cycle");
+        }
+        Ok(())
+    }
+
+    /// Adds fresh copies of the nodes to `graph` and returns their ids and positions, in the
+    /// order of [`WzrdGraphFragment::nodes`]. Nothing is added if the fragment is not valid.
+    pub fn insert(
+        &self,
+        graph: &mut WzrdGraph,
+        user_state: &mut WzrdGraphState,
+        offset: Vec2,
+    ) -> anyhow::Result<Vec<(NodeId, Pos2)>> {
+        self.validate()?;
+        let inserted: Vec<(NodeId, Pos2)> = self
+            .nodes
+            .iter()
+            .map(|fragment_node| {
+                let template = fragment_node.user_data.template.clone();
+                let node_id = graph.add_node(
+                    fragment_node.label.clone(),
+                    fragment_node.user_data.clone(),
+                    |graph, node_id| template.build_node(graph, user_state, node_id),
+                );
+                let input_ids: Vec<_> = graph[node_id]
+                    .inputs
+                    .iter()
+                    .map(|(_, input_id)| *input_id)
+                    .collect();
+                for (input_id, value) in input_ids.iter().zip(fragment_node.input_values.iter()) {
+                    let input = &mut graph.inputs[*input_id];
+                    input.value = value.clone();
+                    // keep constants that were inlined into sockets without a declared value
+                    if !matches!(value, WzrdValueType::Any) {
+                        input.kind = InputParamKind::ConnectionOrConstant;
+                    }
+                }
+                let [x, y] = fragment_node.position;
+                (node_id, pos2(x, y) + offset)
+            })
+            .collect();
+
+        for connection in self.connections.iter() {
+            let (output_node, output_index) = connection.output;
+            let (input_node, input_index) = connection.input;
+            let output_id = graph[inserted[output_node].0]
+                .outputs
+                .get(output_index)
+                .map(|(_, id)| *id);
+            let input_id = graph[inserted[input_node].0]
+                .inputs
+                .get(input_index)
+                .map(|(_, id)| *id);
+            if let (Some(output_id), Some(input_id)) = (output_id, input_id) {
+                graph.add_connection(output_id, input_id);
+            }
+        }
+
+        Ok(inserted)
+    }
+}
+
+impl WzrdNodeGraph {
+    /// Inserts a copy of `fragment` moved by `offset` and returns the new nodes. Their
+    /// statements come after every other statement.
+    pub fn insert_fragment(
+        &mut self,
+        fragment: &WzrdGraphFragment,
+        offset: Vec2,
+    ) -> anyhow::Result<Vec<NodeId>> {
+        let inserted = fragment.insert(&mut self.state.graph, &mut self.user_state, offset)?;
+        for (inserted_id, inserted_position) in inserted.iter() {
+            self.state.node_order.push(*inserted_id);
+            self.state
+                .node_positions
+                .insert(*inserted_id, *inserted_position);
+        }
+        let inserted: Vec<NodeId> = inserted.into_iter().map(|(node_id, _)| node_id).collect();
+        self.place_in_sequence(&inserted, None);
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragments_connecting_unknown_sockets_are_not_inserted() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1.abs\n", None);
+        let node_ids: Vec<NodeId> = graph.state.graph.iter_nodes().collect();
+        let mut fragment =
+            WzrdGraphFragment::extract(&graph.state.graph, &graph.state.node_positions, &node_ids);
+        fragment.connections[0].input.1 = 3;
+
+        assert!(graph.insert_fragment(&fragment, Vec2::ZERO).is_err());
+        assert_eq!(graph.state.graph.nodes.len(), node_ids.len());
+    }
+
+    #[test]
+    fn fragments_connecting_their_nodes_in_a_cycle_are_not_inserted() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1.abs.abs\n", None);
+        let node_ids: Vec<NodeId> = graph.state.graph.iter_nodes().collect();
+        let mut fragment =
+            WzrdGraphFragment::extract(&graph.state.graph, &graph.state.node_positions, &node_ids);
+        // the outer abs feeds the inner one, which already feeds the outer one
+        let inner = fragment
+            .connections
+            .iter()
+            .find(|connection| fragment.nodes[connection.input.0].label == "abs")
+            .copied()
+            .unwrap();
+        fragment.connections.push(WzrdFragmentConnection {
+            output: (inner.input.0, 0),
+            input: (inner.output.0, 0),
+        });
+
+        assert!(graph.insert_fragment(&fragment, Vec2::ZERO).is_err());
+        assert_eq!(graph.state.graph.nodes.len(), node_ids.len());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::graph_core::node::structs::{WzrdFunction, WzrdProjectMethod};
+use crate::graph_core::wzrd_node_graph::{connections_loop, WzrdGraph, WzrdNodeGraph};
+use anyhow::{anyhow, bail, Context};
+use egui_node_graph::NodeId;
+use emath::Pos2;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use slotmap::SecondaryMap;
+
+/// Version written by [`WzrdNodeGraph::to_json`], bumped with every change of the format.
+pub const GRAPH_FILE_VERSION: u64 = 2;
+
+/// A graph saved on its own, without the Ruby file it may have come from.
+#[derive(Serialize, Deserialize)]
+pub struct WzrdGraphFile {
+    pub version: u64,
+    pub graph: WzrdGraph,
+    pub node_positions: SecondaryMap<NodeId, Pos2>,
+    pub node_order: Vec<NodeId>,
+    /// The method the graph is the body of.
+    pub function: Option<WzrdFunction>,
+    /// Methods created by extract method, their code is only part of the graph.
+    #[serde(default)]
+    pub methods: Vec<WzrdProjectMethod>,
+}
+
+/// Version 1 generated statements top to bottom as they were placed on the canvas, version 2
+/// numbers the nodes instead. The numbers are handed out in the old order.
+fn migrate_v1(mut file: Value) -> anyhow::Result<Value> {
+    let position = |index: usize| {
+        let position = file
+            .get("node_positions")
+            .and_then(|positions| positions.get(index))
+            .and_then(|slot| slot.get("value"));
+        let coordinate = |axis: &str| {
+            position
+                .and_then(|position| position.get(axis))
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0)
+        };
+        (coordinate("y"), coordinate("x"))
+    };
+    let slots = file
+        .pointer("/graph/nodes")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("the graph has no nodes"))?;
+    let mut occupied: Vec<(usize, (f64, f64))> = slots
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| !slot["value"].is_null())
+        .map(|(index, _)| (index, position(index)))
+        .collect();
+    occupied.sort_by(|(a, a_position), (b, b_position)| {
+        a_position
+            .partial_cmp(b_position)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.cmp(b))
+    });
+
+    for (sequence, (index, _)) in occupied.into_iter().enumerate() {
+        file["graph"]["nodes"][index]["value"]["user_data"]["sequence"] = json!(sequence);
+    }
+    file["version"] = json!(2);
+    Ok(file)
+}
+
+impl WzrdGraphFile {
+    /// Reads a graph file of any version, migrating it to the current one. Graphs that loop
+    /// back are refused, the editor never connects nodes in a cycle.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let mut value: Value = serde_json::from_str(json).context("not a graph file")?;
+        let mut version = match value.get("version").and_then(Value::as_u64) {
+            Some(version @ 1..) => version,
+            _ => bail!("the graph file has no version"),
+        };
+        if version > GRAPH_FILE_VERSION {
+            bail!("graph file version {version} is newer than this editor supports");
+        }
+        while version < GRAPH_FILE_VERSION {
+            value = match version {
+                1 => migrate_v1(value)?,
+                _ => unreachable!("every version below the current one has a migration"),
+            };
+            version += 1;
+        }
+        let file: Self = serde_json::from_value(value).context("invalid graph file")?;
+        let graph = &file.graph;
+        let node_ids: Vec<NodeId> = graph.iter_nodes().collect();
+        let index = |node_id: NodeId| node_ids.iter().position(|id| *id == node_id);
+        let connections: Vec<(usize, usize)> = graph
+            .connections
+            .iter()
+            .filter_map(|(input_id, output_id)| {
+                let source = index(graph.outputs.get(*output_id)?.node)?;
+                let consumer = index(graph.inputs.get(input_id)?.node)?;
+                Some((source, consumer))
+            })
+            .collect();
+        if connections_loop(node_ids.len(), &connections) {
+            bail!("the graph file connects its nodes in a cycle");
+        }
+        Ok(file)
+    }
+}
+
+impl WzrdNodeGraph {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let file = WzrdGraphFile {
+            version: GRAPH_FILE_VERSION,
+            graph: self.state.graph.clone(),
+            node_positions: self.state.node_positions.clone(),
+            node_order: self.state.node_order.clone(),
+            function: self.function_stack.back().cloned(),
+            methods: self
+                .extracted_methods
+                .iter()
+                .filter_map(|name| self.user_state.project_methods.get(name))
+                .cloned()
+                .collect(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    /// A new editor holding the graph of a graph file, see [`WzrdNodeGraph::open_graph_file`].
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let mut graph = WzrdNodeGraph::new();
+        graph.open_graph_file(WzrdGraphFile::from_json(json)?);
+        Ok(graph)
+    }
+
+    /// Replaces the graph being edited with the one of `file`.
+    pub fn open_graph_file(&mut self, file: WzrdGraphFile) {
+        while !self.open_subgraphs.is_empty() {
+            self.close_subgraph();
+        }
+        self.state.graph = file.graph;
+        self.state.node_positions = file.node_positions;
+        self.state.node_order = file.node_order;
+        self.state.selected_nodes.clear();
+        self.pinned_nodes.clear();
+        self.unplaced_nodes.clear();
+        // a file written by hand may leave nodes out of the layout
+        let graph = &self.state.graph;
+        let unplaced: Vec<NodeId> = graph
+            .iter_nodes()
+            .filter(|node_id| !self.state.node_positions.contains_key(*node_id))
+            .collect();
+        self.state
+            .node_order
+            .retain(|node_id| graph.nodes.contains_key(*node_id));
+        for node_id in unplaced {
+            self.state.node_positions.insert(node_id, Pos2::ZERO);
+            self.state.node_order.push(node_id);
+            self.unplaced_nodes.insert(node_id);
+        }
+        self.format_new_nodes();
+
+        self.user_state.parameters = file
+            .function
+            .as_ref()
+            .map(|function| function.arguments.clone())
+            .unwrap_or_default();
+        self.function_stack = file.function.into_iter().collect();
+        self.extracted_methods = file
+            .methods
+            .iter()
+            .map(|method| method.function.name.clone())
+            .collect();
+        for method in file.methods {
+            self.user_state
+                .project_methods
+                .insert(method.function.name.clone(), method);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "def main(a)\n    b = (a+1)\n    puts b\n    (b*2)\nend\n";
+
+    fn generate(json: &str) -> String {
+        let mut graph = WzrdNodeGraph::from_json(json).unwrap();
+        graph
+            .evaluate_graph(&mut std::collections::HashMap::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn version_1_numbers_statements_by_their_position() {
+        assert_eq!(
+            generate(include_str!("../../../tests/graph_files/v1.json")),
+            CODE
+        );
+    }
+
+    #[test]
+    fn version_2_is_read_as_it_is() {
+        assert_eq!(
+            generate(include_str!("../../../tests/graph_files/v2.json")),
+            CODE
+        );
+    }
+
+    #[test]
+    fn files_without_a_known_version_are_refused() {
+        let v2 = include_str!("../../../tests/graph_files/v2.json");
+        let without_version = v2.replacen("\"version\": 2,", "", 1);
+        assert!(WzrdGraphFile::from_json(&without_version).is_err());
+        let newer = v2.replacen("\"version\": 2,", "\"version\": 3,", 1);
+        assert!(WzrdGraphFile::from_json(&newer).is_err());
+    }
+
+    #[test]
+    fn graphs_that_loop_back_are_refused() {
+        let mut graph =
+            WzrdNodeGraph::from_json(include_str!("../../../tests/graph_files/v2.json")).unwrap();
+        let state = &mut graph.state;
+        let sum = state
+            .graph
+            .iter_nodes()
+            .find(|node_id| state.graph[*node_id].label == "+")
+            .unwrap();
+        let output_id = state.graph[sum].outputs[0].1;
+        let input_id = state.graph[sum].inputs[1].1;
+        state.graph.add_connection(output_id, input_id);
+
+        assert!(WzrdNodeGraph::from_json(&graph.to_json().unwrap()).is_err());
+    }
+}
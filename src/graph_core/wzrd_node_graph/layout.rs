@@ -0,0 +1,734 @@
+use crate::graph_core::node::structs::WzrdNodeData;
+use crate::graph_core::wzrd_node_graph::{stable_hash, WzrdGraph, WzrdNodeGraph};
+use egui_node_graph::{Node, NodeId};
+use emath::{pos2, vec2, Pos2, Rect, Vec2};
+use slotmap::SecondaryMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Horizontal space between two layers.
+const LAYER_GAP: f32 = 60.0;
+/// Vertical space between two nodes of the same layer.
+const NODE_GAP: f32 = 30.0;
+/// Height reserved for an edge passing through a layer.
+const EDGE_HEIGHT: f32 = 10.0;
+const ORDERING_SWEEPS: usize = 8;
+const ALIGNMENT_PASSES: usize = 4;
+
+/// Size of a node that has not been drawn yet, estimated from its label and sockets. Inputs
+/// take two rows, one for their label and one for the widget of their constant.
+pub fn estimate_node_size(node: &Node<WzrdNodeData>) -> Vec2 {
+    const CHAR_WIDTH: f32 = 8.0;
+    const ROW_HEIGHT: f32 = 22.0;
+    let widest = node
+        .inputs
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(node.outputs.iter().map(|(name, _)| name.len()))
+        .chain(std::iter::once(node.label.len()))
+        .max()
+        .unwrap_or(0);
+    let rows = node.inputs.len() * 2 + node.outputs.len();
+    vec2(
+        f32::max(120.0, widest as f32 * CHAR_WIDTH + 60.0),
+        40.0 + rows as f32 * ROW_HEIGHT,
+    )
+}
+
+/// The graph cut into layers, data flows from the higher layers into the lower ones and the
+/// roots make up layer 0. Edges spanning more than one layer are routed through dummy nodes,
+/// so every edge connects neighbouring layers.
+struct LayeredGraph {
+    /// `None` for dummy nodes.
+    nodes: Vec<Option<NodeId>>,
+    layer_of: Vec<usize>,
+    layers: Vec<Vec<usize>>,
+    /// Nodes feeding a node, in the next higher layer.
+    sources: Vec<Vec<usize>>,
+    /// Nodes consuming a node, in the next lower layer.
+    consumers: Vec<Vec<usize>>,
+}
+
+impl LayeredGraph {
+    fn new(graph: &WzrdGraph, roots: &[NodeId]) -> Self {
+        let sources_of = |node_id: NodeId| -> Vec<NodeId> {
+            graph[node_id]
+                .inputs
+                .iter()
+                .filter_map(|(_, input_id)| graph.connection(*input_id))
+                .map(|output_id| graph.get_output(output_id).node)
+                .collect()
+        };
+
+        // upstream from the roots in statement and input order, that order seeds the layers.
+        // Nodes only reachable through a loop follow at the end.
+        let mut node_ids: Vec<NodeId> = vec![];
+        let mut seen: HashSet<NodeId> = HashSet::new();
+        let mut queue: VecDeque<NodeId> = roots.iter().copied().collect();
+        queue.extend(graph.iter_nodes());
+        while let Some(node_id) = queue.pop_front() {
+            if !seen.insert(node_id) {
+                continue;
+            }
+            node_ids.push(node_id);
+            for source in sources_of(node_id).into_iter().rev() {
+                queue.push_front(source);
+            }
+        }
+        let index_of: HashMap<NodeId, usize> = node_ids
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| (*node_id, index))
+            .collect();
+
+        let mut edges: Vec<(usize, usize)> = vec![];
+        for (consumer, node_id) in node_ids.iter().enumerate() {
+            for source in sources_of(*node_id) {
+                let edge = (index_of[&source], consumer);
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        // longest path to a root, so every node sits left of everything it feeds
+        let mut consumers_of: Vec<Vec<usize>> = vec![vec![]; node_ids.len()];
+        for (source, consumer) in edges.iter() {
+            consumers_of[*source].push(*consumer);
+        }
+        fn layer(
+            node: usize,
+            consumers_of: &[Vec<usize>],
+            layers: &mut [Option<usize>],
+            visiting: &mut HashSet<usize>,
+        ) -> usize {
+            if let Some(layer) = layers[node] {
+                return layer;
+            }
+            if !visiting.insert(node) {
+                return 0;
+            }
+            let result = consumers_of[node]
+                .iter()
+                .map(|consumer| layer(*consumer, consumers_of, layers, visiting) + 1)
+                .max()
+                .unwrap_or(0);
+            visiting.remove(&node);
+            layers[node] = Some(result);
+            result
+        }
+        let mut layer_cache: Vec<Option<usize>> = vec![None; node_ids.len()];
+        let mut layer_of: Vec<usize> = (0..node_ids.len())
+            .map(|node| layer(node, &consumers_of, &mut layer_cache, &mut HashSet::new()))
+            .collect();
+
+        let mut nodes: Vec<Option<NodeId>> = node_ids.iter().copied().map(Some).collect();
+        let mut sources: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+        let mut consumers: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+        for (source, consumer) in edges {
+            // an edge closing a loop would point the wrong way, it is left out of the layout
+            if layer_of[source] <= layer_of[consumer] {
+                continue;
+            }
+            let mut lower = consumer;
+            for dummy_layer in layer_of[consumer] + 1..layer_of[source] {
+                let dummy = nodes.len();
+                nodes.push(None);
+                layer_of.push(dummy_layer);
+                sources.push(vec![]);
+                consumers.push(vec![lower]);
+                sources[lower].push(dummy);
+                lower = dummy;
+            }
+            sources[lower].push(source);
+            consumers[source].push(lower);
+        }
+
+        let layer_count = layer_of.iter().max().map_or(0, |max| max + 1);
+        let mut layers: Vec<Vec<usize>> = vec![vec![]; layer_count];
+        // real nodes in traversal order, each dummy right after the node it leads to
+        let mut placed: HashSet<usize> = HashSet::new();
+        fn place(
+            node: usize,
+            sources: &[Vec<usize>],
+            layer_of: &[usize],
+            layers: &mut [Vec<usize>],
+            placed: &mut HashSet<usize>,
+            is_dummy: &dyn Fn(usize) -> bool,
+        ) {
+            if !placed.insert(node) {
+                return;
+            }
+            layers[layer_of[node]].push(node);
+            for source in sources[node].iter() {
+                if is_dummy(*source) {
+                    place(*source, sources, layer_of, layers, placed, is_dummy);
+                }
+            }
+        }
+        let is_dummy = |node: usize| nodes[node].is_none();
+        for node in 0..node_ids.len() {
+            place(
+                node,
+                &sources,
+                &layer_of,
+                &mut layers,
+                &mut placed,
+                &is_dummy,
+            );
+        }
+
+        Self {
+            nodes,
+            layer_of,
+            layers,
+            sources,
+            consumers,
+        }
+    }
+
+    fn positions_in_layer(&self, layer: usize) -> HashMap<usize, f32> {
+        self.layers
+            .get(layer)
+            .map(|nodes| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(position, node)| (*node, position as f32))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sorts a layer by the average position of the neighbours of its nodes, `towards_roots`
+    /// picks the consumers in the layer below instead of the sources in the layer above.
+    fn reorder(&mut self, layer: usize, towards_roots: bool) {
+        let neighbour_layer = if towards_roots { layer - 1 } else { layer + 1 };
+        let neighbour_positions = self.positions_in_layer(neighbour_layer);
+        let mut barycenters: Vec<(usize, f32)> = self.layers[layer]
+            .iter()
+            .enumerate()
+            .map(|(position, node)| {
+                let neighbours = if towards_roots {
+                    &self.consumers[*node]
+                } else {
+                    &self.sources[*node]
+                };
+                let known: Vec<f32> = neighbours
+                    .iter()
+                    .filter_map(|neighbour| neighbour_positions.get(neighbour))
+                    .copied()
+                    .collect();
+                if known.is_empty() {
+                    (*node, position as f32)
+                } else {
+                    (*node, known.iter().sum::<f32>() / known.len() as f32)
+                }
+            })
+            .collect();
+        barycenters.sort_by(|a, b| a.1.total_cmp(&b.1));
+        self.layers[layer] = barycenters.into_iter().map(|(node, _)| node).collect();
+    }
+
+    fn crossings(&self) -> usize {
+        let mut crossings = 0;
+        for layer in 1..self.layers.len() {
+            let lower = self.positions_in_layer(layer - 1);
+            let upper = self.positions_in_layer(layer);
+            let edges: Vec<(f32, f32)> = self.layers[layer]
+                .iter()
+                .flat_map(|source| {
+                    self.consumers[*source]
+                        .iter()
+                        .map(|consumer| (upper[source], lower[consumer]))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            for (i, a) in edges.iter().enumerate() {
+                for b in edges.iter().skip(i + 1) {
+                    if (a.0 - b.0) * (a.1 - b.1) < 0.0 {
+                        crossings += 1;
+                    }
+                }
+            }
+        }
+        crossings
+    }
+
+    /// Barycenter sweeps up and down the layers, keeping the order with the fewest crossings.
+    /// Layer 0 keeps the statement order of the roots, which the generated code depends on.
+    fn minimise_crossings(&mut self) {
+        let mut best = self.layers.clone();
+        let mut best_crossings = self.crossings();
+        for sweep in 0..ORDERING_SWEEPS {
+            if sweep % 2 == 0 {
+                for layer in 1..self.layers.len() {
+                    self.reorder(layer, true);
+                }
+            } else {
+                for layer in (1..self.layers.len().saturating_sub(1)).rev() {
+                    self.reorder(layer, false);
+                }
+            }
+            let crossings = self.crossings();
+            if crossings < best_crossings {
+                best = self.layers.clone();
+                best_crossings = crossings;
+            }
+        }
+        self.layers = best;
+    }
+
+    /// Vertical positions that keep every node close to its neighbours. Each layer is placed
+    /// once pushing overlapping nodes down and once pushing them up, the average of both keeps
+    /// the order and the gaps of the layer.
+    fn assign_y(&self, heights: &[f32]) -> Vec<f32> {
+        let mut tops: Vec<f32> = vec![0.0; self.nodes.len()];
+        for nodes in self.layers.iter() {
+            let mut y = 0.0;
+            for node in nodes {
+                tops[*node] = y;
+                y += heights[*node] + NODE_GAP;
+            }
+        }
+
+        let align = |tops: &mut Vec<f32>, layer: usize, towards_roots: bool| {
+            let nodes = &self.layers[layer];
+            let desired: Vec<f32> = nodes
+                .iter()
+                .map(|node| {
+                    let neighbours = if towards_roots {
+                        &self.consumers[*node]
+                    } else {
+                        &self.sources[*node]
+                    };
+                    let centre = |node: &usize| tops[*node] + heights[*node] / 2.0;
+                    if neighbours.is_empty() {
+                        centre(node) - heights[*node] / 2.0
+                    } else {
+                        neighbours.iter().map(centre).sum::<f32>() / neighbours.len() as f32
+                            - heights[*node] / 2.0
+                    }
+                })
+                .collect();
+
+            let mut down = desired.clone();
+            for i in 1..nodes.len() {
+                down[i] = down[i].max(down[i - 1] + heights[nodes[i - 1]] + NODE_GAP);
+            }
+            let mut up = desired;
+            for i in (0..nodes.len().saturating_sub(1)).rev() {
+                up[i] = up[i].min(up[i + 1] - heights[nodes[i]] - NODE_GAP);
+            }
+            for (i, node) in nodes.iter().enumerate() {
+                tops[*node] = (down[i] + up[i]) / 2.0;
+            }
+        };
+
+        for _ in 0..ALIGNMENT_PASSES {
+            for layer in 1..self.layers.len() {
+                align(&mut tops, layer, true);
+            }
+            for layer in (0..self.layers.len().saturating_sub(1)).rev() {
+                align(&mut tops, layer, false);
+            }
+        }
+        tops
+    }
+}
+
+fn neighbours(graph: &WzrdGraph, node_id: NodeId) -> Vec<NodeId> {
+    let node = &graph[node_id];
+    let sources = node
+        .inputs
+        .iter()
+        .filter_map(|(_, input_id)| graph.connection(*input_id))
+        .map(|output_id| graph.get_output(output_id).node);
+    let consumers = graph
+        .connections
+        .iter()
+        .filter(|(_, output_id)| node.outputs.iter().any(|(_, id)| id == *output_id))
+        .map(|(input_id, _)| graph.inputs[input_id].node);
+    sources.chain(consumers).collect()
+}
+
+/// Layered layout of the whole graph, roots on the right in their statement order and every
+/// node left of the nodes it feeds. `size_of` gives the size of a node.
+pub fn layered_layout(
+    graph: &WzrdGraph,
+    roots: &[NodeId],
+    size_of: impl Fn(NodeId) -> Vec2,
+) -> SecondaryMap<NodeId, Pos2> {
+    let mut layered = LayeredGraph::new(graph, roots);
+    layered.minimise_crossings();
+
+    let sizes: Vec<Vec2> = layered
+        .nodes
+        .iter()
+        .map(|node| node.map_or(vec2(0.0, EDGE_HEIGHT), &size_of))
+        .collect();
+    let heights: Vec<f32> = sizes.iter().map(|size| size.y).collect();
+    let tops = layered.assign_y(&heights);
+
+    let mut layer_x: Vec<f32> = vec![];
+    let mut x = 0.0;
+    for nodes in layered.layers.iter() {
+        let width = nodes.iter().map(|node| sizes[*node].x).fold(0.0, f32::max);
+        x -= width;
+        layer_x.push(x);
+        x -= LAYER_GAP;
+    }
+
+    let mut positions: SecondaryMap<NodeId, Pos2> = SecondaryMap::new();
+    for (index, node) in layered.nodes.iter().enumerate() {
+        if let Some(node_id) = node {
+            positions.insert(
+                *node_id,
+                pos2(layer_x[layered.layer_of[index]], tops[index]),
+            );
+        }
+    }
+
+    // start the layout at the origin
+    let min = positions
+        .values()
+        .fold(pos2(f32::INFINITY, f32::INFINITY), |min, position| {
+            min.min(*position)
+        });
+    if min.x.is_finite() {
+        for position in positions.values_mut() {
+            *position -= min.to_vec2();
+        }
+    }
+    positions
+}
+
+impl WzrdNodeGraph {
+    /// Size of a node as last drawn, or estimated if it has not been drawn yet.
+    pub fn node_size(&self, node_id: NodeId) -> Vec2 {
+        self.state
+            .node_rects
+            .get(&node_id)
+            .map(|rect| rect.size())
+            .unwrap_or_else(|| estimate_node_size(&self.state.graph[node_id]))
+    }
+
+    /// Positions of a layered layout of the graph, usable without a UI.
+    pub fn layout_positions(&self) -> SecondaryMap<NodeId, Pos2> {
+        layered_layout(&self.state.graph, &self.find_root_nodes(), |node_id| {
+            self.node_size(node_id)
+        })
+    }
+
+    /// Places the nodes that have no position of their own yet next to their neighbours, at the
+    /// offsets a full layout would give them. Every other node stays where it is.
+    pub fn format_new_nodes(&mut self) {
+        let graph = &self.state.graph;
+        let new_nodes: Vec<NodeId> = graph
+            .iter_nodes()
+            .filter(|node_id| {
+                self.unplaced_nodes.contains(node_id) && !self.pinned_nodes.contains(node_id)
+            })
+            .collect();
+        self.unplaced_nodes.clear();
+        if new_nodes.is_empty() {
+            return;
+        }
+
+        let ideal = self.layout_positions();
+        let mut positions = self.state.node_positions.clone();
+        let mut placed: HashSet<NodeId> = graph
+            .iter_nodes()
+            .filter(|node_id| !new_nodes.contains(node_id))
+            .collect();
+        let mut remaining = new_nodes.clone();
+        while !remaining.is_empty() {
+            // keep the offsets of the full layout towards the neighbours that are already placed
+            let anchored: Vec<(NodeId, Pos2)> = remaining
+                .iter()
+                .filter_map(|node_id| {
+                    let offsets: Vec<Vec2> = neighbours(graph, *node_id)
+                        .into_iter()
+                        .filter(|neighbour| placed.contains(neighbour))
+                        .map(|neighbour| positions[neighbour] - ideal[neighbour])
+                        .collect();
+                    if offsets.is_empty() {
+                        return None;
+                    }
+                    let offset = offsets.iter().fold(Vec2::ZERO, |sum, offset| sum + *offset)
+                        / offsets.len() as f32;
+                    Some((*node_id, ideal[*node_id] + offset))
+                })
+                .collect();
+
+            if anchored.is_empty() {
+                // nothing connects the rest to the placed nodes, it goes below all of them
+                let bottom = placed
+                    .iter()
+                    .map(|node_id| positions[*node_id].y + self.node_size(*node_id).y)
+                    .fold(f32::NEG_INFINITY, f32::max);
+                let left = placed
+                    .iter()
+                    .map(|node_id| positions[*node_id].x)
+                    .fold(f32::INFINITY, f32::min);
+                let top_left = remaining
+                    .iter()
+                    .map(|node_id| ideal[*node_id])
+                    .fold(pos2(f32::INFINITY, f32::INFINITY), |min, position| {
+                        min.min(position)
+                    });
+                let offset = if bottom.is_finite() {
+                    pos2(left, bottom + NODE_GAP) - top_left
+                } else {
+                    Vec2::ZERO
+                };
+                for node_id in remaining.drain(..) {
+                    positions.insert(node_id, ideal[node_id] + offset);
+                }
+                break;
+            }
+
+            for (node_id, position) in anchored {
+                positions.insert(node_id, position);
+                placed.insert(node_id);
+                remaining.retain(|remaining_id| *remaining_id != node_id);
+            }
+        }
+
+        // move new nodes down until they no longer cover another node
+        let rect = |positions: &SecondaryMap<NodeId, Pos2>, node_id: NodeId| {
+            Rect::from_min_size(positions[node_id], self.node_size(node_id))
+        };
+        for node_id in new_nodes.iter() {
+            for _ in 0..graph.nodes.len() {
+                let own = rect(&positions, *node_id);
+                let covered = graph
+                    .iter_nodes()
+                    .filter(|other| other != node_id)
+                    .map(|other| rect(&positions, other))
+                    .find(|other| other.intersects(own));
+                match covered {
+                    Some(other) => positions[*node_id].y = other.max.y + NODE_GAP,
+                    None => break,
+                }
+            }
+        }
+        self.state.node_positions = positions;
+    }
+
+    /// Where each node sits in the code: the statement it belongs to and the inputs leading from
+    /// that statement to the node. Statements are told apart by a hash of what they compute, so
+    /// adding or removing a statement leaves the paths of the other statements alone. Identical
+    /// statements are numbered, shared nodes get the path they are first reached by.
+    pub fn node_paths(&self) -> HashMap<NodeId, String> {
+        let graph = &self.state.graph;
+        let mut signatures: HashMap<NodeId, String> = HashMap::new();
+        let mut paths: HashMap<NodeId, String> = HashMap::new();
+        let mut statements: HashMap<String, usize> = HashMap::new();
+        let mut queue: VecDeque<NodeId> = VecDeque::new();
+        for root in self.find_root_nodes() {
+            let signature = self.signature(root, &mut signatures);
+            let statement = format!("{:}@{:016x}", graph[root].label, stable_hash(&signature));
+            let count = statements.entry(statement.clone()).or_default();
+            paths.insert(root, format!("{statement}#{count}"));
+            *count += 1;
+            queue.push_back(root);
+        }
+        while let Some(node_id) = queue.pop_front() {
+            for (index, (_, input_id)) in graph[node_id].inputs.iter().enumerate() {
+                let source = match graph.connection(*input_id) {
+                    Some(output_id) => graph.get_output(output_id).node,
+                    None => continue,
+                };
+                if !paths.contains_key(&source) {
+                    let path = format!("{:}/{index}:{:}", paths[&node_id], graph[source].label);
+                    paths.insert(source, path);
+                    queue.push_back(source);
+                }
+            }
+        }
+        paths
+    }
+
+    /// What the node computes: its label and outputs, then for every input the signature of the
+    /// node connected to it or the value it holds.
+    fn signature(&self, node_id: NodeId, signatures: &mut HashMap<NodeId, String>) -> String {
+        if let Some(signature) = signatures.get(&node_id) {
+            return signature.clone();
+        }
+        // keeps a graph that loops back from recursing forever
+        signatures.insert(node_id, String::new());
+
+        let graph = &self.state.graph;
+        let node = &graph[node_id];
+        let outputs: Vec<&str> = node.outputs.iter().map(|(name, _)| name.as_str()).collect();
+        let inputs: Vec<String> = node
+            .inputs
+            .iter()
+            .map(|(_, input_id)| match graph.connection(*input_id) {
+                Some(output_id) => self.signature(graph.get_output(output_id).node, signatures),
+                None => graph.get_input(*input_id).value.to_literal(),
+            })
+            .collect();
+        let signature = format!(
+            "{:}[{:}]({:})",
+            node.label,
+            outputs.join(","),
+            inputs.join(",")
+        );
+        signatures.insert(node_id, signature.clone());
+        signature
+    }
+
+    /// Rebuilds the graph from `code`, as after an edit in the text editor. Nodes found at the
+    /// same place of the code keep their position and pin, only the others are laid out.
+    pub fn reimport(&mut self, code: &str) {
+        while !self.open_subgraphs.is_empty() {
+            self.close_subgraph();
+        }
+        let mut previous: HashMap<String, (Pos2, bool)> = self
+            .node_paths()
+            .into_iter()
+            .filter_map(|(node_id, path)| {
+                let position = self.state.node_positions.get(node_id)?;
+                Some((path, (*position, self.pinned_nodes.contains(&node_id))))
+            })
+            .collect();
+
+        self.state.node_positions = SecondaryMap::new();
+        self.state.node_order.clear();
+        self.state.selected_nodes.clear();
+        self.pinned_nodes.clear();
+        self.unplaced_nodes.clear();
+        let mut graph = WzrdGraph::new();
+        let mut user_state = std::mem::take(&mut self.user_state);
+        self.initialize_graph(&mut graph, &mut user_state, code);
+        self.state.graph = graph;
+        self.user_state = user_state;
+
+        for (node_id, path) in self.node_paths() {
+            if let Some((position, pinned)) = previous.remove(&path) {
+                self.state.node_positions.insert(node_id, position);
+                self.unplaced_nodes.remove(&node_id);
+                if pinned {
+                    self.pinned_nodes.insert(node_id);
+                }
+            }
+        }
+        self.format_new_nodes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The node labelled `label` whose first input holds `value`.
+    fn node(graph: &WzrdNodeGraph, label: &str, value: &str) -> NodeId {
+        let graph = &graph.state.graph;
+        graph
+            .iter_nodes()
+            .find(|node_id| {
+                let node = &graph[*node_id];
+                node.label == label
+                    && node
+                        .inputs
+                        .first()
+                        .map(|(_, input_id)| graph.get_input(*input_id).value.to_literal())
+                        == Some(value.into())
+            })
+            .expect("no node with the label holds the value")
+    }
+
+    /// Rects of every node, with their estimated size.
+    fn rects(graph: &WzrdNodeGraph) -> Vec<(NodeId, Rect)> {
+        graph
+            .state
+            .graph
+            .iter_nodes()
+            .map(|node_id| {
+                let position = graph.state.node_positions[node_id];
+                (
+                    node_id,
+                    Rect::from_min_size(position, graph.node_size(node_id)),
+                )
+            })
+            .collect()
+    }
+
+    fn assert_no_overlaps(graph: &WzrdNodeGraph) {
+        let rects = rects(graph);
+        for (index, (node_id, rect)) in rects.iter().enumerate() {
+            for (other_id, other) in rects.iter().skip(index + 1) {
+                assert!(
+                    !rect.intersects(*other),
+                    "{:} covers {:}",
+                    graph.state.graph[*node_id].label,
+                    graph.state.graph[*other_id].label
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layout_places_shared_nodes_once_left_of_every_consumer() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1.abs + 2\nputs 3.abs\nputs 4\n", None);
+        // the second abs feeds both statements
+        let shared = node(&graph, "abs", "3");
+        let sum = graph
+            .state
+            .graph
+            .iter_nodes()
+            .find(|node_id| graph.state.graph[*node_id].label == "+")
+            .unwrap();
+        let output_id = graph.state.graph[shared].outputs[0].1;
+        let input_id = graph.state.graph[sum].inputs[1].1;
+        graph.state.graph.add_connection(output_id, input_id);
+        graph.format_graph();
+        assert_no_overlaps(&graph);
+
+        let state = &graph.state;
+        for (input_id, output_id) in state.graph.connections.iter() {
+            let source = state.graph.get_output(*output_id).node;
+            let consumer = state.graph.get_input(input_id).node;
+            assert!(
+                state.node_positions[source].x + graph.node_size(source).x
+                    <= state.node_positions[consumer].x,
+                "{:} is not left of {:}",
+                state.graph[source].label,
+                state.graph[consumer].label
+            );
+        }
+    }
+
+    #[test]
+    fn new_nodes_are_placed_without_moving_or_covering_the_others() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1.abs\n", None);
+        graph.format_graph();
+        let old_position = graph.state.node_positions[node(&graph, "abs", "1")];
+
+        graph.reimport("puts 1.abs\nputs 2.abs\n");
+        assert_eq!(
+            graph.state.node_positions[node(&graph, "abs", "1")],
+            old_position
+        );
+        assert!(graph.unplaced_nodes.is_empty());
+        assert_no_overlaps(&graph);
+    }
+
+    #[test]
+    fn reimport_keeps_moved_nodes_when_a_statement_is_inserted_before_them() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.reimport("puts 1\nputs 2\n");
+        let moved = node(&graph, "puts", "2");
+        graph.state.node_positions.insert(moved, pos2(500.0, 500.0));
+        graph.pinned_nodes.insert(moved);
+
+        graph.reimport("puts 0\nputs 1\nputs 2\n");
+        let moved = node(&graph, "puts", "2");
+        assert_eq!(graph.state.node_positions[moved], pos2(500.0, 500.0));
+        assert!(graph.pinned_nodes.contains(&moved));
+        assert!(!graph.pinned_nodes.contains(&node(&graph, "puts", "0")));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::graph_core::node::structs::WzrdValueType;
+use crate::graph_core::wzrd_node_graph::WzrdNodeGraph;
+use egui_node_graph::NodeId;
+use std::collections::HashSet;
+
+impl WzrdNodeGraph {
+    /// The structure of the graph as text, one line per statement in the order the code is
+    /// generated in. Positions and ids are left out, so two graphs built from the same code
+    /// have the same outline.
+    pub fn outline(&self) -> String {
+        self.find_root_nodes()
+            .into_iter()
+            .map(|root| self.outline_node(root, &mut HashSet::new()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn outline_node(&self, node_id: NodeId, visiting: &mut HashSet<NodeId>) -> String {
+        let graph = &self.state.graph;
+        let node = &graph[node_id];
+        let template = &node.user_data.template;
+        let label = match template.outputs.first() {
+            Some(variable) if template.label == "Variable" => {
+                format!("Variable:{:}", variable.name)
+            }
+            _ => node.label.clone(),
+        };
+        if !visiting.insert(node_id) {
+            return format!("<loop {label}>");
+        }
+
+        let inputs: Vec<String> = node
+            .inputs
+            .iter()
+            .map(|(_, input_id)| match graph.connection(*input_id) {
+                Some(output_id) => {
+                    let source = graph.get_output(output_id).node;
+                    let outline = self.outline_node(source, visiting);
+                    // only nodes with several outputs need to tell which one is used
+                    match graph[source].outputs.as_slice() {
+                        [_] => outline,
+                        outputs => {
+                            let name = outputs
+                                .iter()
+                                .find(|(_, id)| *id == output_id)
+                                .map(|(name, _)| name.as_str())
+                                .unwrap_or_default();
+                            format!("{outline}.{name}")
+                        }
+                    }
+                }
+                None => match &graph.inputs[*input_id].value {
+                    WzrdValueType::Any => "_".into(),
+                    value => value.to_literal(),
+                },
+            })
+            .collect();
+        visiting.remove(&node_id);
+
+        if inputs.is_empty() {
+            label
+        } else {
+            format!("{label}({:})", inputs.join(", "))
+        }
+    }
+}
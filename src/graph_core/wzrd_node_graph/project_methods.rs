@@ -0,0 +1,232 @@
+use crate::graph_core::node::structs::{
+    WzrdFunction, WzrdGraphState, WzrdProjectMethod, WzrdType, WzrdValueType,
+};
+use crate::graph_core::wzrd_node_graph::{RNode, WzrdNodeGraph};
+use lib_ruby_parser::{Parser, ParserOptions, ParserResult};
+use log::debug;
+use std::ops::Deref;
+
+/// Category of the call nodes generated for methods defined in the workspace.
+pub const PROJECT: &str = "Project";
+
+#[derive(Debug, Clone)]
+pub(super) struct Parameter {
+    pub(super) name: String,
+    keyword: bool,
+}
+
+pub(super) fn parse_parameters(node: &RNode) -> Vec<Parameter> {
+    let positional = |name: &str| Parameter {
+        name: name.into(),
+        keyword: false,
+    };
+    let keyword = |name: &str| Parameter {
+        name: name.into(),
+        keyword: true,
+    };
+
+    match node {
+        RNode::Args(args) => args.args.iter().flat_map(parse_parameters).collect(),
+        RNode::Arg(arg) => vec![positional(&arg.name)],
+        RNode::Optarg(arg) => vec![positional(&arg.name)],
+        RNode::Kwarg(arg) => vec![keyword(&arg.name)],
+        RNode::Kwoptarg(arg) => vec![keyword(&arg.name)],
+        _ => vec![],
+    }
+}
+
+/// The parameters of a method as they are written in `code`, without their parentheses.
+pub(super) fn parameters_source(code: &str, args: &RNode) -> Option<String> {
+    let location = args.expression();
+    let source = code.get(location.begin..location.end)?.trim();
+    let source = source
+        .strip_prefix('(')
+        .and_then(|source| source.strip_suffix(')'))
+        .unwrap_or(source);
+    Some(source.trim().to_string()).filter(|source| !source.is_empty())
+}
+
+fn call_template(name: &str, parameters: &[Parameter]) -> String {
+    if parameters.is_empty() {
+        return name.into();
+    }
+    let arguments: Vec<String> = parameters
+        .iter()
+        .enumerate()
+        .map(|(i, parameter)| {
+            if parameter.keyword {
+                format!("{}: ${i}", parameter.name)
+            } else {
+                format!("${i}")
+            }
+        })
+        .collect();
+    format!("{name}({})", arguments.join(", "))
+}
+
+impl WzrdNodeGraph {
+    /// Parses `code` and offers every method defined in it as a call node. `path` is `None` for
+    /// the document that is being edited.
+    pub fn index_project_methods(
+        &mut self,
+        user_state: &mut WzrdGraphState,
+        path: Option<&str>,
+        code: &str,
+    ) {
+        let parser = Parser::new(code, ParserOptions::default());
+        let ParserResult { ast, .. } = parser.do_parse();
+
+        if let Some(node) = ast {
+            self.index_definitions(user_state, path, code, node.deref());
+        }
+    }
+
+    pub(super) fn index_definitions(
+        &mut self,
+        user_state: &mut WzrdGraphState,
+        path: Option<&str>,
+        code: &str,
+        node: &RNode,
+    ) {
+        match node {
+            RNode::Begin(begin) => {
+                for statement in begin.statements.iter() {
+                    self.index_definitions(user_state, path, code, statement);
+                }
+            }
+            RNode::Class(class) => {
+                if let Some(body) = &class.body {
+                    self.index_definitions(user_state, path, code, body.deref());
+                }
+            }
+            RNode::Module(module) => {
+                if let Some(body) = &module.body {
+                    self.index_definitions(user_state, path, code, body.deref());
+                }
+            }
+            RNode::Def(def) => {
+                let parameters = def
+                    .args
+                    .as_ref()
+                    .map(|args| parse_parameters(args.deref()))
+                    .unwrap_or_default();
+                let begin = def.expression_l.begin;
+                let end = def.expression_l.end;
+
+                let method = WzrdProjectMethod {
+                    function: WzrdFunction {
+                        name: def.name.to_string(),
+                        arguments: parameters
+                            .iter()
+                            .map(|parameter| parameter.name.clone())
+                            .collect(),
+                        signature: def
+                            .args
+                            .as_ref()
+                            .and_then(|args| parameters_source(code, args)),
+                    },
+                    path: path.map(String::from),
+                    line: code[..begin].matches('\n').count() + 1,
+                    source: code[begin..end].to_string(),
+                };
+                self.register_project_method(user_state, method, &parameters);
+            }
+            _ => {}
+        }
+    }
+
+    fn register_project_method(
+        &mut self,
+        user_state: &mut WzrdGraphState,
+        method: WzrdProjectMethod,
+        parameters: &[Parameter],
+    ) {
+        let name = method.function.name.clone();
+        // a method imported again replaces the old version of itself, not other nodes
+        if user_state.project_methods.contains_key(&name) {
+            self.unregister_project_method(user_state, &name);
+        } else if self.node_templates.find(&name).is_some() {
+            debug!("{name} is already known, not indexing it as a project method");
+            return;
+        }
+
+        self.node_templates.create_node(
+            &name,
+            Some(call_template(&name, parameters)),
+            Some(PROJECT.into()),
+            None,
+            parameters
+                .iter()
+                .enumerate()
+                .map(|(i, parameter)| WzrdType {
+                    name: parameter.name.clone(),
+                    data_type: WzrdValueType::Any,
+                    order: i as u8 + 1,
+                })
+                .collect(),
+            vec![WzrdType {
+                name: "out".into(),
+                data_type: WzrdValueType::Any,
+                order: 1,
+            }],
+        );
+        user_state.project_methods.insert(name, method);
+    }
+
+    pub(super) fn unregister_project_method(
+        &mut self,
+        user_state: &mut WzrdGraphState,
+        name: &str,
+    ) {
+        if user_state.project_methods.remove(name).is_some() {
+            self.node_templates.remove(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn importing_a_method_again_replaces_it() {
+        let mut graph = WzrdNodeGraph::new();
+        let mut user_state = WzrdGraphState::default();
+        graph.index_project_methods(&mut user_state, Some("a.rb"), "def helper(a)\n  a\nend\n");
+        graph.index_project_methods(
+            &mut user_state,
+            Some("a.rb"),
+            "def helper(a, b)\n  a + b\nend\n",
+        );
+
+        let template = graph.node_templates.find("helper").unwrap();
+        assert_eq!(template.template.as_deref(), Some("helper($0, $1)"));
+        assert_eq!(
+            user_state.project_methods["helper"].function.arguments,
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn only_calls_without_a_receiver_become_project_method_calls() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(
+            "def run(list)\n  helper(list)\n  list.helper(1)\nend\n\ndef helper(a)\n  a\nend\n",
+            None,
+        );
+
+        assert!(graph.node_templates.find("helper").is_some());
+        assert!(graph.node_templates.find("run").is_none());
+        let categories: Vec<Option<String>> = graph
+            .state
+            .graph
+            .nodes
+            .values()
+            .filter(|node| node.label == "helper")
+            .map(|node| node.user_data.template.category.clone())
+            .collect();
+        assert_eq!(categories.len(), 2);
+        assert!(categories.contains(&Some(PROJECT.into())));
+        assert!(categories.contains(&None));
+    }
+}
@@ -0,0 +1,410 @@
+use crate::graph_core::node::structs::{WzrdFunction, WzrdGraphState, WzrdValueType};
+use crate::graph_core::node::WzrdNodes;
+use crate::graph_core::wzrd_node_graph::fragment::WzrdGraphFragment;
+use crate::graph_core::wzrd_node_graph::{NodeCache, WzrdGraph, WzrdNodeGraph};
+use anyhow::{anyhow, bail};
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait, OutputId};
+use emath::{Pos2, Vec2};
+use std::collections::HashMap;
+
+/// Name given to methods created by [`WzrdNodeGraph::extract_method`], numbered if taken.
+const EXTRACTED_METHOD: &str = "extracted_method";
+
+fn unique_name(base: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let mut name = base.to_string();
+    let mut counter = 2;
+    while is_taken(&name) {
+        name = format!("{base}{counter}");
+        counter += 1;
+    }
+    name
+}
+
+/// Turns a socket or variable name into something that can be used as a Ruby parameter.
+fn parameter_name(name: &str) -> String {
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match name.chars().next() {
+        Some(first) if !first.is_ascii_digit() => name,
+        _ => format!("arg{name}"),
+    }
+}
+
+impl WzrdNodeGraph {
+    /// Moves `selection` into a new method of the document and replaces it with a call to the
+    /// method, which is returned. Values flowing into the selection become the parameters of
+    /// the method, the one value used after the selection becomes its result.
+    pub fn extract_method(&mut self, selection: &[NodeId]) -> anyhow::Result<NodeId> {
+        if selection.is_empty() {
+            bail!("select the nodes to extract first");
+        }
+
+        let graph = &self.state.graph;
+        let mut parameters: Vec<(String, OutputId)> = vec![];
+        let mut bound_inputs: Vec<((usize, usize), usize)> = vec![];
+        let mut results: Vec<((usize, usize), Vec<InputId>)> = vec![];
+        for (node_index, node_id) in selection.iter().enumerate() {
+            let node = &graph[*node_id];
+            for (input_index, (socket_name, input_id)) in node.inputs.iter().enumerate() {
+                let output_id = match graph.connection(*input_id) {
+                    Some(output_id) => output_id,
+                    None => continue,
+                };
+                let source = &graph[graph.get_output(output_id).node];
+                if selection.contains(&source.id) {
+                    continue;
+                }
+                // a value consumed by several nodes of the selection is passed only once
+                let parameter = match parameters.iter().position(|(_, id)| *id == output_id) {
+                    Some(parameter) => parameter,
+                    None => {
+                        let base = match source.user_data.template.outputs.first() {
+                            Some(variable) if source.user_data.template.label == "Variable" => {
+                                parameter_name(&variable.name)
+                            }
+                            _ => parameter_name(socket_name),
+                        };
+                        let name = unique_name(&base, |name| {
+                            parameters.iter().any(|(taken, _)| taken == name)
+                        });
+                        parameters.push((name, output_id));
+                        parameters.len() - 1
+                    }
+                };
+                bound_inputs.push(((node_index, input_index), parameter));
+            }
+
+            for (output_index, (_, output_id)) in node.outputs.iter().enumerate() {
+                let consumers: Vec<InputId> = self
+                    .consumers_of(*output_id)
+                    .into_iter()
+                    .filter(|input_id| !selection.contains(&graph.inputs[*input_id].node))
+                    .collect();
+                if !consumers.is_empty() {
+                    results.push(((node_index, output_index), consumers));
+                }
+            }
+        }
+        if results.len() > 1 {
+            bail!(
+                "{:} values of the selection are used outside of it, a method can only return one",
+                results.len()
+            );
+        }
+
+        let name = unique_name(EXTRACTED_METHOD, |name| self.find_template(name).is_some());
+        let fragment = WzrdGraphFragment::extract(graph, &self.state.node_positions, selection);
+
+        // generate the method on a graph of its own, with the parameters pasted into the inputs
+        let mut method = WzrdNodeGraph::new();
+        let inserted = fragment.insert(
+            &mut method.state.graph,
+            &mut WzrdGraphState::default(),
+            Vec2::ZERO,
+        )?;
+        for (inserted_id, inserted_position) in inserted.iter() {
+            method
+                .state
+                .node_positions
+                .insert(*inserted_id, *inserted_position);
+        }
+        for ((node_index, input_index), parameter) in bound_inputs.iter() {
+            let input_id = method.state.graph[inserted[*node_index].0].inputs[*input_index].1;
+            method.state.graph.inputs[input_id].value = WzrdValueType::Expression {
+                value: parameters[*parameter].0.clone(),
+            };
+        }
+        if let Some(((node_index, output_index), _)) = results.first() {
+            let result_id = method.state.graph[inserted[*node_index].0].outputs[*output_index].1;
+            let output = WzrdNodes::Output.node();
+            let output_id = method.state.graph.add_node(
+                output.label.clone(),
+                output.user_data(&mut method.user_state),
+                |graph, node_id| output.build_node(graph, &mut WzrdGraphState::default(), node_id),
+            );
+            let input_id = method.state.graph[output_id].inputs[0].1;
+            method.state.graph.add_connection(result_id, input_id);
+        }
+        method.function_stack.push_back(WzrdFunction {
+            name: name.clone(),
+            arguments: parameters.iter().map(|(name, _)| name.clone()).collect(),
+            signature: None,
+        });
+        let mut cache: NodeCache = HashMap::new();
+        let source = method.evaluate_graph(&mut cache)?;
+
+        let mut user_state = std::mem::take(&mut self.user_state);
+        self.index_project_methods(&mut user_state, None, &source);
+        self.user_state = user_state;
+        let template = self
+            .find_template(&name)
+            .ok_or_else(|| anyhow!("could not parse the extracted method:\n{source}"))?;
+
+        // the call takes the place of the first statement it replaces
+        let sequence = selection
+            .iter()
+            .map(|node_id| self.state.graph[*node_id].user_data.sequence)
+            .min()
+            .unwrap_or_default();
+        for node_id in selection {
+            self.remove_node(*node_id);
+        }
+        let call_id = self.state.graph.add_node(
+            name.clone(),
+            template.user_data(&mut self.user_state),
+            |graph, node_id| template.build_node(graph, &mut self.user_state, node_id),
+        );
+        self.state.graph[call_id].user_data.sequence = sequence;
+        self.state.node_order.push(call_id);
+        self.state.node_positions.insert(call_id, fragment.center());
+
+        let call = self.state.graph[call_id].clone();
+        for ((_, output_id), (_, input_id)) in parameters.iter().zip(call.inputs.iter()) {
+            self.state.graph.add_connection(*output_id, *input_id);
+        }
+        if let (Some((_, consumers)), Some((_, output_id))) =
+            (results.first(), call.outputs.first())
+        {
+            for input_id in consumers.iter() {
+                self.state.graph.add_connection(*output_id, *input_id);
+            }
+        }
+        self.extracted_methods.push(name.clone());
+
+        // the method is appended to the document, only now its line is known
+        let document = self.evaluate_graph(&mut HashMap::new())?;
+        let definition = format!("def {name}");
+        if let Some(line) = document
+            .lines()
+            .position(|line| line.trim_start().starts_with(&definition))
+        {
+            if let Some(method) = self.user_state.project_methods.get_mut(&name) {
+                method.line = line + 1;
+            }
+        }
+        Ok(call_id)
+    }
+
+    /// Replaces a call to a workspace method by the nodes of its body, with the arguments of the
+    /// call in place of the parameters. Returns the nodes of the body.
+    pub fn inline_method(&mut self, node_id: NodeId) -> anyhow::Result<Vec<NodeId>> {
+        let call = self.state.graph[node_id].clone();
+        let name = call.user_data.template.label.clone();
+        let source = self
+            .user_state
+            .project_methods
+            .get(&name)
+            .map(|method| method.source.clone())
+            .ok_or_else(|| anyhow!("{name} is not a method of this workspace"))?;
+
+        let mut method = WzrdNodeGraph::new();
+        method.node_templates = self.node_templates.clone();
+        let mut method_graph = WzrdGraph::new();
+        method.initialize_graph(&mut method_graph, &mut self.user_state.clone(), &source);
+        method.state.graph = method_graph;
+        let parameters = method
+            .function_stack
+            .back()
+            .map(|function| function.arguments.clone())
+            .unwrap_or_default();
+
+        let roots = method.find_root_nodes();
+        let output_nodes: Vec<NodeId> = method
+            .state
+            .graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.user_data.template.label == "output")
+            .map(|(id, _)| id)
+            .collect();
+        if output_nodes.len() > 1 || output_nodes.iter().any(|id| !roots.contains(id)) {
+            bail!("{name} returns early and can not be inlined");
+        }
+        let result: Option<OutputId> = match output_nodes.first() {
+            Some(output_node) => method
+                .state
+                .graph
+                .connection(method.state.graph[*output_node].inputs[0].1),
+            None => roots
+                .last()
+                .and_then(|root| method.state.graph[*root].outputs.first())
+                .map(|(_, output_id)| *output_id),
+        };
+
+        let node_ids: Vec<NodeId> = method
+            .state
+            .graph
+            .nodes
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !output_nodes.contains(id))
+            .collect();
+        let fragment = WzrdGraphFragment::extract(
+            &method.state.graph,
+            &method.state.node_positions,
+            &node_ids,
+        );
+        let position = self
+            .state
+            .node_positions
+            .get(node_id)
+            .copied()
+            .unwrap_or(Pos2::ZERO);
+        let inserted = fragment.insert(
+            &mut self.state.graph,
+            &mut self.user_state,
+            position - fragment.center(),
+        )?;
+        // the parsed nodes all start at the same spot, they are placed once wired up
+        for (inserted_id, inserted_position) in inserted.iter() {
+            self.state.node_order.push(*inserted_id);
+            self.state
+                .node_positions
+                .insert(*inserted_id, *inserted_position);
+            self.unplaced_nodes.insert(*inserted_id);
+        }
+
+        // the value an output of the method corresponds to, once it is part of this graph
+        let located = |output_id: OutputId, graph: &WzrdGraph| {
+            let node = &method.state.graph[method.state.graph.get_output(output_id).node];
+            let node_index = node_ids.iter().position(|id| *id == node.id)?;
+            let output_index = node.outputs.iter().position(|(_, id)| *id == output_id)?;
+            Some(graph[inserted[node_index].0].outputs[output_index].1)
+        };
+        let mut result = result.and_then(|output_id| located(output_id, &self.state.graph));
+
+        // parameters are read through Variable nodes, those are replaced by the arguments
+        let mut substituted = vec![];
+        for (inserted_id, _) in inserted.iter() {
+            let node = self.state.graph[*inserted_id].clone();
+            if node.user_data.template.label != "Variable" {
+                continue;
+            }
+            let parameter = match node
+                .user_data
+                .template
+                .outputs
+                .first()
+                .and_then(|variable| parameters.iter().position(|name| *name == variable.name))
+            {
+                Some(parameter) => parameter,
+                None => continue,
+            };
+            let variable_output = node.outputs[0].1;
+            let argument_input = call.inputs.get(parameter).map(|(_, id)| *id);
+            let argument = argument_input.and_then(|id| self.state.graph.connection(id));
+            for consumer in self.consumers_of(variable_output) {
+                self.state.graph.remove_connection(consumer);
+                match (argument, argument_input) {
+                    (Some(argument), _) => self.state.graph.add_connection(argument, consumer),
+                    (None, Some(argument_input)) => {
+                        self.state.graph.inputs[consumer].value =
+                            self.state.graph.inputs[argument_input].value.clone();
+                    }
+                    (None, None) => {}
+                }
+            }
+            if result == Some(variable_output) {
+                result = argument;
+            }
+            substituted.push(*inserted_id);
+        }
+
+        let inserted_ids: Vec<NodeId> = inserted.iter().map(|(id, _)| *id).collect();
+        self.place_in_sequence(&inserted_ids, Some(call.user_data.sequence));
+
+        let consumers: Vec<InputId> = call
+            .outputs
+            .iter()
+            .flat_map(|(_, output_id)| self.consumers_of(*output_id))
+            .collect();
+        for removed_id in substituted.iter().copied().chain([node_id]) {
+            self.remove_node(removed_id);
+        }
+        if let Some(result) = result {
+            for input_id in consumers {
+                self.state.graph.add_connection(result, input_id);
+            }
+        }
+
+        let still_called = self
+            .state
+            .graph
+            .nodes
+            .iter()
+            .any(|(_, node)| node.user_data.template.label == name);
+        if !still_called {
+            self.extracted_methods
+                .retain(|extracted| *extracted != name);
+        }
+        self.format_new_nodes();
+        Ok(inserted
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|id| !substituted.contains(id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "class Greeter
+  def greet(name)
+    puts name.upcase
+  end
+
+  def helper(a)
+    a
+  end
+end
+";
+
+    #[test]
+    fn extracted_methods_are_put_after_the_edited_method() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(CODE, None);
+        let selection: Vec<NodeId> = graph
+            .state
+            .graph
+            .iter_nodes()
+            .filter(|node_id| graph.state.graph[*node_id].label == "upcase")
+            .collect();
+        graph.pinned_nodes.extend(selection.iter().copied());
+        graph.extract_method(&selection).unwrap();
+        // nothing is kept about the nodes that moved into the method
+        assert!(graph.pinned_nodes.is_empty());
+        assert!(selection
+            .iter()
+            .all(|node_id| !graph.unplaced_nodes.contains(node_id)));
+
+        let document = graph.evaluate_graph(&mut HashMap::new()).unwrap();
+        assert_eq!(
+            document,
+            "class Greeter
+  def greet(name)
+      puts extracted_method(name)
+  end
+
+  def extracted_method(name)
+      return name.upcase
+  end
+
+  def helper(a)
+    a
+  end
+end
+"
+        );
+
+        // the document now holds the method, the same method is edited after parsing it again
+        graph.load_document(&document, None);
+        assert_eq!(graph.function_stack.back().unwrap().name, "greet");
+        assert!(graph.extracted_methods.is_empty());
+        assert_eq!(graph.evaluate_graph(&mut HashMap::new()).unwrap(), document);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::graph_core::wzrd_node_graph::{stable_hash, WzrdGraph, WzrdNodeGraph, PERSISTENCE_KEY};
+use emath::pos2;
+#[cfg(feature = "persistence")]
+use log::warn;
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Starts the comment at the end of a document that holds the positions of its nodes.
+pub const LAYOUT_COMMENT: &str = "# wzrd-layout: ";
+
+/// Positions of the nodes of a document, keyed by [`WzrdNodeGraph::node_paths`] so they still
+/// match once the code has been parsed again.
+#[derive(Default)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct WzrdSavedLayout {
+    positions: BTreeMap<String, [i32; 2]>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pinned: Vec<String>,
+}
+
+#[cfg(feature = "persistence")]
+impl WzrdSavedLayout {
+    /// The layout in the last layout comment of `code`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let json = code
+            .lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix(LAYOUT_COMMENT.trim_end()))?;
+        serde_json::from_str(json.trim())
+            .map_err(|err| warn!("ignoring the layout comment of the document: {err}"))
+            .ok()
+    }
+
+    pub fn to_comment(&self) -> String {
+        format!(
+            "{LAYOUT_COMMENT}{:}",
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
+impl WzrdNodeGraph {
+    pub fn saved_layout(&self) -> WzrdSavedLayout {
+        let mut layout = WzrdSavedLayout::default();
+        for (node_id, path) in self.node_paths() {
+            if let Some(position) = self.state.node_positions.get(node_id) {
+                if self.pinned_nodes.contains(&node_id) {
+                    layout.pinned.push(path.clone());
+                }
+                layout
+                    .positions
+                    .insert(path, [position.x.round() as i32, position.y.round() as i32]);
+            }
+        }
+        layout.pinned.sort();
+        layout
+    }
+
+    /// Moves the nodes to their saved positions and lays out the nodes the layout does not know.
+    /// Returns whether any node was found in the layout.
+    pub fn apply_saved_layout(&mut self, layout: &WzrdSavedLayout) -> bool {
+        let mut restored = false;
+        for (node_id, path) in self.node_paths() {
+            if let Some([x, y]) = layout.positions.get(&path) {
+                self.state
+                    .node_positions
+                    .insert(node_id, pos2(*x as f32, *y as f32));
+                self.unplaced_nodes.remove(&node_id);
+                if layout.pinned.contains(&path) {
+                    self.pinned_nodes.insert(node_id);
+                }
+                restored = true;
+            }
+        }
+        if restored {
+            self.format_new_nodes();
+        }
+        restored
+    }
+
+    /// Builds the graph of a document and restores its layout, from the layout comment of the
+    /// document or else from `stored`, the layout saved under the
+    /// [`WzrdNodeGraph::persistence_key`] of the document. Without either the graph is laid out
+    /// from scratch once the editor is shown.
+    pub fn load_document(&mut self, code: &str, stored: Option<WzrdSavedLayout>) {
+        let mut graph = WzrdGraph::new();
+        let mut user_state = self.user_state.clone();
+        self.initialize_graph(&mut graph, &mut user_state, code);
+        self.state.graph = graph;
+        self.user_state = user_state;
+
+        #[cfg(feature = "persistence")]
+        let layout = WzrdSavedLayout::from_code(code).or(stored);
+        #[cfg(not(feature = "persistence"))]
+        let layout = stored;
+        let restored = match layout {
+            Some(layout) => self.apply_saved_layout(&layout),
+            None => false,
+        };
+        *self.format_requested.lock().unwrap() = !restored;
+    }
+
+    /// The generated document followed by its layout comment, what gets written to the file.
+    #[cfg(feature = "persistence")]
+    pub fn evaluate_document(&mut self) -> anyhow::Result<String> {
+        let document = self.evaluate_graph(&mut HashMap::new())?;
+        Ok(format!(
+            "{:}\n{:}\n",
+            document.trim_end(),
+            self.saved_layout().to_comment()
+        ))
+    }
+
+    /// The generated document, its layout can only be written with the `persistence` feature.
+    #[cfg(not(feature = "persistence"))]
+    pub fn evaluate_document(&mut self) -> anyhow::Result<String> {
+        let document = self.evaluate_graph(&mut HashMap::new())?;
+        Ok(format!("{:}\n", document.trim_end()))
+    }
+
+    /// Storage key of the layout of the document `code`, a hash of its statements so documents
+    /// do not share a layout. Indentation, blank lines and the layout comment are left out, the
+    /// key of a document stays the same once the editor has written it.
+    pub fn persistence_key(code: &str) -> String {
+        let statements: Vec<&str> = code
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with(LAYOUT_COMMENT.trim_end()))
+            .collect();
+        format!(
+            "{PERSISTENCE_KEY}/{:016x}",
+            stable_hash(&statements.join("\n"))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_defining_the_same_method_have_their_own_key() {
+        let first = "def main(a)\n  puts a\nend\n";
+        let second = "def main(a)\n  print a\nend\n";
+        assert_ne!(
+            WzrdNodeGraph::persistence_key(first),
+            WzrdNodeGraph::persistence_key(second)
+        );
+        assert_eq!(
+            WzrdNodeGraph::persistence_key(first),
+            WzrdNodeGraph::persistence_key(&format!("\n    {first}{LAYOUT_COMMENT}{{}}\n"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "persistence")]
+    fn the_layout_comment_restores_moved_nodes() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document("puts 1\nputs 2\n", None);
+        let moved = graph.find_root_nodes()[1];
+        graph.state.node_positions.insert(moved, pos2(500.0, 500.0));
+        graph.pinned_nodes.insert(moved);
+        let document = graph.evaluate_document().unwrap();
+
+        let mut reopened = WzrdNodeGraph::new();
+        reopened.load_document(&document, None);
+        let moved = reopened.find_root_nodes()[1];
+        assert_eq!(reopened.state.node_positions[moved], pos2(500.0, 500.0));
+        assert!(reopened.pinned_nodes.contains(&moved));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::graph_core::node::structs::{
+    WzrdNode, WzrdNodeData, WzrdSubgraph, WzrdType, WzrdValueType,
+};
+use crate::graph_core::wzrd_node_graph::fragment::WzrdGraphFragment;
+use crate::graph_core::wzrd_node_graph::{WzrdEditorState, WzrdNodeGraph};
+use anyhow::bail;
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait, OutputId};
+use emath::{Pos2, Vec2};
+
+pub const SUBGRAPH: &str = "Subgraph";
+
+/// A subgraph that is being edited in its own tab, `parent` is the editor state of the tab it
+/// was opened from.
+pub struct WzrdOpenSubgraph {
+    pub node_id: NodeId,
+    pub parent: WzrdEditorState,
+    inputs: Vec<InputId>,
+    outputs: Vec<OutputId>,
+}
+
+impl WzrdSubgraph {
+    /// Checks that the sockets of the subgraph node point at sockets inside the fragment, see
+    /// [`WzrdGraphFragment::validate`].
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.fragment.validate()?;
+        let inputs = self.inputs.iter().map(|socket| (socket, false));
+        let outputs = self.outputs.iter().map(|socket| (socket, true));
+        for (socket, output) in inputs.chain(outputs) {
+            if !self.fragment.has_socket(*socket, output) {
+                bail!("the subgraph refers to a socket it does not have: {socket:?}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WzrdNodeGraph {
+    /// Replaces `selection` with a single subgraph node. Its sockets are the connections
+    /// crossing the border of the selection.
+    pub fn collapse_nodes(&mut self, selection: &[NodeId]) -> Option<NodeId> {
+        if selection.is_empty() {
+            return None;
+        }
+
+        let graph = &self.state.graph;
+        let fragment = WzrdGraphFragment::extract(graph, &self.state.node_positions, selection);
+
+        let mut inputs: Vec<((usize, usize), WzrdType, OutputId)> = vec![];
+        let mut outputs: Vec<((usize, usize), WzrdType, Vec<InputId>)> = vec![];
+        for (node_index, node_id) in selection.iter().enumerate() {
+            let node = &graph[*node_id];
+            let declared_type = |sockets: &Vec<WzrdType>, index: usize| {
+                sockets
+                    .get(index)
+                    .map(|socket| socket.data_type.clone())
+                    .unwrap_or(WzrdValueType::Any)
+            };
+
+            for (input_index, (name, input_id)) in node.inputs.iter().enumerate() {
+                if let Some(output_id) = graph.connection(*input_id) {
+                    if !selection.contains(&graph.get_output(output_id).node) {
+                        let socket = WzrdType {
+                            name: name.clone(),
+                            data_type: declared_type(&node.user_data.template.inputs, input_index),
+                            order: inputs.len() as u8 + 1,
+                        };
+                        inputs.push(((node_index, input_index), socket, output_id));
+                    }
+                }
+            }
+
+            for (output_index, (name, output_id)) in node.outputs.iter().enumerate() {
+                let consumers: Vec<InputId> = self
+                    .consumers_of(*output_id)
+                    .into_iter()
+                    .filter(|input_id| !selection.contains(&graph.inputs[*input_id].node))
+                    .collect();
+                if !consumers.is_empty() {
+                    let socket = WzrdType {
+                        name: name.clone(),
+                        data_type: declared_type(&node.user_data.template.outputs, output_index),
+                        order: outputs.len() as u8 + 1,
+                    };
+                    outputs.push(((node_index, output_index), socket, consumers));
+                }
+            }
+        }
+
+        let template = WzrdNode {
+            template: None,
+            label: SUBGRAPH.into(),
+            category: None,
+            color: Some([90, 60, 130]),
+            inputs: inputs.iter().map(|(_, socket, _)| socket.clone()).collect(),
+            outputs: outputs
+                .iter()
+                .map(|(_, socket, _)| socket.clone())
+                .collect(),
+        };
+        let position = fragment.center();
+        // the subgraph takes the place of the first statement it replaces
+        let sequence = selection
+            .iter()
+            .map(|node_id| graph[*node_id].user_data.sequence)
+            .min()
+            .unwrap_or_default();
+        let user_data = WzrdNodeData {
+            template: template.clone(),
+            subgraph: Some(WzrdSubgraph {
+                fragment,
+                inputs: inputs.iter().map(|(socket, _, _)| *socket).collect(),
+                outputs: outputs.iter().map(|(socket, _, _)| *socket).collect(),
+            }),
+            sequence,
+        };
+
+        for node_id in selection {
+            self.remove_node(*node_id);
+        }
+        let node_id = self
+            .state
+            .graph
+            .add_node(SUBGRAPH.into(), user_data, |graph, node_id| {
+                template.build_node(graph, &mut self.user_state, node_id)
+            });
+        self.state.node_order.push(node_id);
+        self.state.node_positions.insert(node_id, position);
+
+        let new_node = self.state.graph[node_id].clone();
+        for ((_, _, output_id), (_, input_id)) in inputs.iter().zip(new_node.inputs.iter()) {
+            self.state.graph.add_connection(*output_id, *input_id);
+        }
+        for ((_, _, consumers), (_, output_id)) in outputs.iter().zip(new_node.outputs.iter()) {
+            for input_id in consumers.iter() {
+                self.state.graph.add_connection(*output_id, *input_id);
+            }
+        }
+
+        Some(node_id)
+    }
+
+    /// Puts the nodes of a subgraph back in place of the subgraph node and returns them.
+    pub fn expand_subgraph(&mut self, node_id: NodeId) -> anyhow::Result<Vec<NodeId>> {
+        let node = self.state.graph[node_id].clone();
+        let subgraph = match node.user_data.subgraph {
+            Some(subgraph) => subgraph,
+            None => return Ok(vec![]),
+        };
+        subgraph.validate()?;
+
+        let outer_inputs: Vec<Option<OutputId>> = node
+            .inputs
+            .iter()
+            .map(|(_, input_id)| self.state.graph.connection(*input_id))
+            .collect();
+        let outer_outputs: Vec<Vec<InputId>> = node
+            .outputs
+            .iter()
+            .map(|(_, output_id)| self.consumers_of(*output_id))
+            .collect();
+        let position = self
+            .state
+            .node_positions
+            .get(node_id)
+            .copied()
+            .unwrap_or(Pos2::ZERO);
+        let sequence = node.user_data.sequence;
+
+        self.remove_node(node_id);
+        let inserted = subgraph.fragment.insert(
+            &mut self.state.graph,
+            &mut self.user_state,
+            position - subgraph.fragment.center(),
+        )?;
+        for (inserted_id, inserted_position) in inserted.iter() {
+            self.state.node_order.push(*inserted_id);
+            self.state
+                .node_positions
+                .insert(*inserted_id, *inserted_position);
+        }
+
+        let inserted_ids: Vec<NodeId> = inserted.iter().map(|(id, _)| *id).collect();
+        self.place_in_sequence(&inserted_ids, Some(sequence));
+
+        let graph = &mut self.state.graph;
+        for ((node_index, input_index), outer_output) in subgraph.inputs.iter().zip(outer_inputs) {
+            if let Some(output_id) = outer_output {
+                let input_id = graph[inserted[*node_index].0].inputs[*input_index].1;
+                graph.add_connection(output_id, input_id);
+            }
+        }
+        for ((node_index, output_index), consumers) in subgraph.outputs.iter().zip(outer_outputs) {
+            let output_id = graph[inserted[*node_index].0].outputs[*output_index].1;
+            for input_id in consumers {
+                graph.add_connection(output_id, input_id);
+            }
+        }
+
+        Ok(inserted_ids)
+    }
+
+    /// Opens the subgraph of `node_id` in a new tab.
+    pub fn open_subgraph(&mut self, node_id: NodeId) -> anyhow::Result<()> {
+        let subgraph = match self.state.graph[node_id].user_data.subgraph.clone() {
+            Some(subgraph) => subgraph,
+            None => return Ok(()),
+        };
+        subgraph.validate()?;
+
+        let mut state = WzrdEditorState::default();
+        let inserted =
+            subgraph
+                .fragment
+                .insert(&mut state.graph, &mut self.user_state, Vec2::ZERO)?;
+        for (inserted_id, inserted_position) in inserted.iter() {
+            state.node_order.push(*inserted_id);
+            state
+                .node_positions
+                .insert(*inserted_id, *inserted_position);
+        }
+        let inputs = subgraph
+            .inputs
+            .iter()
+            .map(|(node_index, input_index)| {
+                state.graph[inserted[*node_index].0].inputs[*input_index].1
+            })
+            .collect();
+        let outputs = subgraph
+            .outputs
+            .iter()
+            .map(|(node_index, output_index)| {
+                state.graph[inserted[*node_index].0].outputs[*output_index].1
+            })
+            .collect();
+
+        let parent = std::mem::replace(&mut self.state, state);
+        self.open_subgraphs.push(WzrdOpenSubgraph {
+            node_id,
+            parent,
+            inputs,
+            outputs,
+        });
+        *self.format_requested.lock().unwrap() = true;
+        Ok(())
+    }
+
+    /// Closes the innermost subgraph tab and stores the edited nodes in its subgraph node.
+    pub fn close_subgraph(&mut self) {
+        let open = match self.open_subgraphs.pop() {
+            Some(open) => open,
+            None => return,
+        };
+        let edited = std::mem::replace(&mut self.state, open.parent);
+
+        let node_ids: Vec<NodeId> = edited.graph.nodes.iter().map(|(id, _)| id).collect();
+        let fragment = WzrdGraphFragment::extract(&edited.graph, &edited.node_positions, &node_ids);
+        let inputs: Vec<Option<(usize, usize)>> = open
+            .inputs
+            .iter()
+            .map(|input_id| {
+                let node_id = edited.graph.inputs.get(*input_id)?.node;
+                let node_index = node_ids.iter().position(|id| *id == node_id)?;
+                let input_index = edited.graph[node_id]
+                    .inputs
+                    .iter()
+                    .position(|(_, id)| id == input_id)?;
+                Some((node_index, input_index))
+            })
+            .collect();
+        let outputs: Vec<Option<(usize, usize)>> = open
+            .outputs
+            .iter()
+            .map(|output_id| {
+                let node_id = edited.graph.outputs.get(*output_id)?.node;
+                let node_index = node_ids.iter().position(|id| *id == node_id)?;
+                let output_index = edited.graph[node_id]
+                    .outputs
+                    .iter()
+                    .position(|(_, id)| id == output_id)?;
+                Some((node_index, output_index))
+            })
+            .collect();
+
+        // sockets whose inner node was deleted in the tab disappear from the subgraph node
+        let node = self.state.graph[open.node_id].clone();
+        let mut template = node.user_data.template.clone();
+        for (index, located) in inputs.iter().enumerate().rev() {
+            if located.is_none() {
+                self.state.graph.remove_input_param(node.inputs[index].1);
+                template.inputs.remove(index);
+            }
+        }
+        for (index, located) in outputs.iter().enumerate().rev() {
+            if located.is_none() {
+                self.state.graph.remove_output_param(node.outputs[index].1);
+                template.outputs.remove(index);
+            }
+        }
+
+        let user_data = &mut self.state.graph.nodes[open.node_id].user_data;
+        user_data.template = template;
+        user_data.subgraph = Some(WzrdSubgraph {
+            fragment,
+            inputs: inputs.into_iter().flatten().collect(),
+            outputs: outputs.into_iter().flatten().collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_core::wzrd_node_graph::NodeCache;
+
+    fn nodes_labelled(graph: &WzrdNodeGraph, labels: &[&str]) -> Vec<NodeId> {
+        graph
+            .state
+            .graph
+            .iter_nodes()
+            .filter(|node_id| labels.contains(&graph.state.graph[*node_id].label.as_str()))
+            .collect()
+    }
+
+    const CODE: &str = "def f(list)\n    puts list.size\n    x = list.first\n    x\nend\n";
+
+    #[test]
+    fn collapsed_nodes_generate_every_statement_once() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(CODE, None);
+        let selection = nodes_labelled(&graph, &["puts", "size", "first"]);
+        let subgraph = graph.collapse_nodes(&selection).unwrap();
+        let output_id = graph.state.graph[subgraph].outputs[0].1;
+        assert_eq!(graph.consumers_of(output_id).len(), 1);
+
+        assert_eq!(graph.evaluate_graph(&mut NodeCache::new()).unwrap(), CODE);
+    }
+
+    #[test]
+    fn subgraphs_with_unknown_sockets_are_refused() {
+        let mut graph = WzrdNodeGraph::new();
+        graph.load_document(CODE, None);
+        let selection = nodes_labelled(&graph, &["size", "first"]);
+        let node_id = graph.collapse_nodes(&selection).unwrap();
+        if let Some(subgraph) = &mut graph.state.graph[node_id].user_data.subgraph {
+            subgraph.outputs.push((7, 0));
+        }
+
+        assert!(graph.expand_subgraph(node_id).is_err());
+        assert!(graph.open_subgraph(node_id).is_err());
+        assert!(graph.open_subgraphs.is_empty());
+    }
+}
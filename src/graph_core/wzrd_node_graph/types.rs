@@ -0,0 +1,463 @@
+use crate::graph_core::node::structs::{WzrdNodeDataType, WzrdValueType};
+use crate::graph_core::node::WzrdNodes;
+use crate::graph_core::wzrd_node_graph::{WzrdGraph, WzrdNodeGraph};
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait, OutputId};
+use emath::Pos2;
+use std::collections::{HashMap, HashSet};
+
+impl WzrdNodeDataType {
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            WzrdNodeDataType::Integer | WzrdNodeDataType::Float | WzrdNodeDataType::Number
+        )
+    }
+
+    /// Whether an input of this type takes a value of type `value` as it is. `Any` and
+    /// `Expression` take every value, `Integer` and `Float` widen to `Number`. A value of
+    /// unknown type is given the benefit of the doubt.
+    pub fn accepts(&self, value: &WzrdNodeDataType) -> bool {
+        match (self, value) {
+            (WzrdNodeDataType::Any | WzrdNodeDataType::Expression, _)
+            | (_, WzrdNodeDataType::Any | WzrdNodeDataType::Expression) => true,
+            (WzrdNodeDataType::Number, value) => value.is_numeric(),
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+
+    /// The conversion node that turns a value of type `value` into one this type accepts.
+    pub fn conversion_from(&self, value: &WzrdNodeDataType) -> Option<WzrdNodes> {
+        if self.accepts(value) {
+            return None;
+        }
+        let convertible = value.is_numeric() || matches!(value, WzrdNodeDataType::String);
+        match self {
+            WzrdNodeDataType::String => Some(WzrdNodes::ToS),
+            WzrdNodeDataType::Integer if convertible => Some(WzrdNodes::ToI),
+            WzrdNodeDataType::Float | WzrdNodeDataType::Number if convertible => {
+                Some(WzrdNodes::ToF)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether an output can be connected to an input, see [`WzrdConnectionCheck::new`].
+#[derive(PartialEq)]
+pub enum WzrdConnectionCheck {
+    Valid,
+    /// The input takes the value once it went through the conversion node.
+    Convertible(WzrdNodes),
+    /// The node owning the output already depends on the node owning the input.
+    Cycle,
+    Invalid,
+}
+
+impl WzrdConnectionCheck {
+    /// Checks connecting `output_id` to `input_id` of `graph`, before they get connected.
+    pub fn new(graph: &WzrdGraph, output_id: OutputId, input_id: InputId) -> Self {
+        if creates_cycle(graph, output_id, input_id) {
+            return WzrdConnectionCheck::Cycle;
+        }
+        let value = &graph.outputs[output_id].typ;
+        let expected = &graph.inputs[input_id].typ;
+        if expected.accepts(value) {
+            WzrdConnectionCheck::Valid
+        } else {
+            match expected.conversion_from(value) {
+                Some(conversion) => WzrdConnectionCheck::Convertible(conversion),
+                None => WzrdConnectionCheck::Invalid,
+            }
+        }
+    }
+}
+
+/// Checks whether connecting `output_id` to `input_id` closes a loop, that is whether the
+/// node owning the output already depends on the node owning the input.
+fn creates_cycle(graph: &WzrdGraph, output_id: OutputId, input_id: InputId) -> bool {
+    let source_node_id = graph.get_output(output_id).node;
+    let target_node_id = graph.inputs[input_id].node;
+
+    let mut visited: HashSet<NodeId> = HashSet::new();
+    let mut stack = vec![source_node_id];
+    while let Some(node_id) = stack.pop() {
+        if node_id == target_node_id {
+            return true;
+        }
+        if !visited.insert(node_id) {
+            continue;
+        }
+        stack.extend(
+            graph[node_id]
+                .inputs
+                .iter()
+                .filter_map(|(_, input_id)| graph.connection(*input_id))
+                .map(|output_id| graph.get_output(output_id).node),
+        );
+    }
+    false
+}
+
+/// A connection that was dropped because of its types, waiting for the user to decide whether a
+/// conversion node should be put in between.
+#[derive(Clone)]
+pub struct WzrdPendingConversion {
+    pub output: OutputId,
+    pub input: InputId,
+    pub output_type: WzrdNodeDataType,
+    pub input_type: WzrdNodeDataType,
+    pub conversion: WzrdNodes,
+}
+
+/// The most specific type the result of arithmetic on `a` and `b` fits in.
+fn numeric_result(a: &WzrdNodeDataType, b: &WzrdNodeDataType) -> WzrdNodeDataType {
+    match (a, b) {
+        (WzrdNodeDataType::Integer, WzrdNodeDataType::Integer) => WzrdNodeDataType::Integer,
+        (WzrdNodeDataType::Float, other) | (other, WzrdNodeDataType::Float)
+            if other.is_numeric() =>
+        {
+            WzrdNodeDataType::Float
+        }
+        _ if a.is_numeric() && b.is_numeric() => WzrdNodeDataType::Number,
+        _ => WzrdNodeDataType::Any,
+    }
+}
+
+/// Signature rules of the standard nodes. `None` leaves the declared output type in place.
+fn signature(label: &str, inputs: &[WzrdNodeDataType]) -> Option<WzrdNodeDataType> {
+    let first = inputs.first().cloned().unwrap_or(WzrdNodeDataType::Any);
+    let second = inputs.get(1).cloned().unwrap_or(WzrdNodeDataType::Any);
+    let inferred = match label {
+        "+" => match (&first, &second) {
+            (WzrdNodeDataType::String, WzrdNodeDataType::String) => WzrdNodeDataType::String,
+            _ => numeric_result(&first, &second),
+        },
+        "*" => match (&first, &second) {
+            (WzrdNodeDataType::String, WzrdNodeDataType::Integer) => WzrdNodeDataType::String,
+            _ => numeric_result(&first, &second),
+        },
+        "-" | "/" | "%" | "**" => numeric_result(&first, &second),
+        "abs" | "max" | "min" if first.is_numeric() => first,
+        "round" | "floor" | "ceil" | "length" | "size" | "to_i" => WzrdNodeDataType::Integer,
+        "to_f" => WzrdNodeDataType::Float,
+        "to_sym" => WzrdNodeDataType::Symbol,
+        "==" | "!=" | "<" | ">" | "<=" | ">=" | "!" | "nil?" | "empty?" | "include?"
+        | "start_with?" | "end_with?" => WzrdNodeDataType::Boolean,
+        "&&" | "||" => match (&first, &second) {
+            (WzrdNodeDataType::Boolean, WzrdNodeDataType::Boolean) => WzrdNodeDataType::Boolean,
+            _ => WzrdNodeDataType::Any,
+        },
+        "upcase" | "downcase" | "capitalize" | "strip" | "gsub" | "join" | "to_s" => {
+            WzrdNodeDataType::String
+        }
+        "reverse" if matches!(first, WzrdNodeDataType::String) => WzrdNodeDataType::String,
+        "If" => {
+            let then_type = second;
+            let else_type = inputs.get(2).cloned().unwrap_or(WzrdNodeDataType::Any);
+            match (&then_type, &else_type) {
+                (a, b) if a.is_numeric() && b.is_numeric() => numeric_result(a, b),
+                (a, b) if std::mem::discriminant(a) == std::mem::discriminant(b) => then_type,
+                _ => WzrdNodeDataType::Any,
+            }
+        }
+        _ => return None,
+    };
+    Some(inferred)
+}
+
+/// Walks the graph upstream from every node, the types of the outputs are memoized per node.
+struct TypeInference<'a> {
+    graph: &'a WzrdGraph,
+    variables: HashMap<String, WzrdNodeDataType>,
+    outputs: HashMap<NodeId, Vec<WzrdNodeDataType>>,
+}
+
+impl<'a> TypeInference<'a> {
+    fn input_type(
+        &mut self,
+        input_id: InputId,
+        visiting: &mut HashSet<NodeId>,
+    ) -> WzrdNodeDataType {
+        let graph = self.graph;
+        match graph.connection(input_id) {
+            Some(output_id) => {
+                let node_id = graph.get_output(output_id).node;
+                let index = graph[node_id]
+                    .outputs
+                    .iter()
+                    .position(|(_, id)| *id == output_id)
+                    .unwrap_or(0);
+                self.output_types(node_id, visiting)
+                    .get(index)
+                    .cloned()
+                    .unwrap_or(WzrdNodeDataType::Any)
+            }
+            None => graph.inputs[input_id].value.clone().into(),
+        }
+    }
+
+    fn output_types(
+        &mut self,
+        node_id: NodeId,
+        visiting: &mut HashSet<NodeId>,
+    ) -> Vec<WzrdNodeDataType> {
+        if let Some(types) = self.outputs.get(&node_id) {
+            return types.clone();
+        }
+
+        let graph = self.graph;
+        let node = &graph[node_id];
+        let template = &node.user_data.template;
+        let mut types: Vec<WzrdNodeDataType> = template
+            .outputs
+            .iter()
+            .map(|output| output.data_type.clone().into())
+            .collect();
+        // a loop in the graph has no meaningful type, it is refused when connecting anyway
+        if !visiting.insert(node_id) {
+            return types;
+        }
+        let inputs: Vec<WzrdNodeDataType> = node
+            .inputs
+            .iter()
+            .map(|(_, input_id)| self.input_type(*input_id, visiting))
+            .collect();
+        visiting.remove(&node_id);
+
+        let inferred = match template.label.as_str() {
+            "Variable" => template
+                .outputs
+                .first()
+                .and_then(|variable| self.variables.get(&variable.name))
+                .cloned(),
+            "Constant" | "output" => inputs.first().cloned(),
+            "Block" => inputs.last().cloned(),
+            _ if node.user_data.subgraph.is_some() => None,
+            label => signature(label, &inputs),
+        };
+        if let (Some(inferred), Some(first)) = (inferred, types.first_mut()) {
+            *first = inferred;
+        }
+
+        self.outputs.insert(node_id, types.clone());
+        types
+    }
+
+    /// Types of the local variables, a variable assigned values of different types is `Any`.
+    fn assigned_variables(&mut self) -> HashMap<String, WzrdNodeDataType> {
+        let graph = self.graph;
+        let mut variables: HashMap<String, WzrdNodeDataType> = HashMap::new();
+        for (_, node) in graph.nodes.iter() {
+            if node.user_data.template.label != "Assignment" || node.inputs.len() < 2 {
+                continue;
+            }
+            let target = node.inputs[1].1;
+            let name = match &graph.inputs[target].value {
+                WzrdValueType::Expression { value } if graph.connection(target).is_none() => {
+                    value.clone()
+                }
+                _ => continue,
+            };
+            let value_type = self.input_type(node.inputs[0].1, &mut HashSet::new());
+            variables
+                .entry(name)
+                .and_modify(|known| {
+                    if std::mem::discriminant(known) != std::mem::discriminant(&value_type) {
+                        *known = WzrdNodeDataType::Any;
+                    }
+                })
+                .or_insert(value_type);
+        }
+        variables
+    }
+}
+
+impl WzrdNodeGraph {
+    /// Propagates types from literals and assignments through the signature rules of the nodes
+    /// and stores them as the types of the output sockets. Parameters are untyped, so anything
+    /// computed from them stays `Any` unless a rule says otherwise.
+    pub fn infer_types(&mut self) {
+        let mut inference = TypeInference {
+            graph: &self.state.graph,
+            variables: HashMap::new(),
+            outputs: HashMap::new(),
+        };
+        // variables are only known once the values assigned to them are
+        inference.variables = inference.assigned_variables();
+        inference.outputs.clear();
+
+        let node_ids: Vec<NodeId> = self.state.graph.iter_nodes().collect();
+        let mut inferred = vec![];
+        for node_id in node_ids {
+            let types = inference.output_types(node_id, &mut HashSet::new());
+            for ((_, output_id), data_type) in self.state.graph[node_id].outputs.iter().zip(types) {
+                inferred.push((*output_id, data_type));
+            }
+        }
+
+        for (output_id, data_type) in inferred {
+            self.state.graph.outputs[output_id].typ = data_type;
+        }
+    }
+
+    /// Connects the output and input of `pending` through its conversion node.
+    pub fn insert_conversion(&mut self, pending: &WzrdPendingConversion) {
+        let graph = &self.state.graph;
+        // either end may have been deleted while the suggestion was shown
+        if !graph.outputs.contains_key(pending.output) || !graph.inputs.contains_key(pending.input)
+        {
+            return;
+        }
+        let position = |node_id: NodeId| {
+            self.state
+                .node_positions
+                .get(node_id)
+                .copied()
+                .unwrap_or(Pos2::ZERO)
+        };
+        let from = position(graph.get_output(pending.output).node);
+        let to = position(graph.inputs[pending.input].node);
+
+        let template = pending.conversion.node();
+        let node_id = self.state.graph.add_node(
+            template.label.clone(),
+            template.user_data(&mut self.user_state),
+            |graph, node_id| template.build_node(graph, &mut self.user_state, node_id),
+        );
+        self.state.node_order.push(node_id);
+        self.state
+            .node_positions
+            .insert(node_id, from + (to - from) / 2.0);
+
+        let node = self.state.graph[node_id].clone();
+        if let (Some((_, input_id)), Some((_, output_id))) =
+            (node.inputs.first(), node.outputs.first())
+        {
+            self.state.graph.add_connection(pending.output, *input_id);
+            self.state.graph.add_connection(*output_id, pending.input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_infer_their_result_from_their_operands() {
+        assert_eq!(
+            signature("+", &[WzrdNodeDataType::String, WzrdNodeDataType::String]),
+            Some(WzrdNodeDataType::String)
+        );
+        assert_eq!(
+            signature("*", &[WzrdNodeDataType::String, WzrdNodeDataType::Integer]),
+            Some(WzrdNodeDataType::String)
+        );
+        assert_eq!(
+            signature("+", &[WzrdNodeDataType::Integer, WzrdNodeDataType::Float]),
+            Some(WzrdNodeDataType::Float)
+        );
+        assert_eq!(
+            signature("*", &[WzrdNodeDataType::Integer, WzrdNodeDataType::Integer]),
+            Some(WzrdNodeDataType::Integer)
+        );
+        assert_eq!(
+            signature("-", &[WzrdNodeDataType::String, WzrdNodeDataType::String]),
+            Some(WzrdNodeDataType::Any)
+        );
+        assert_eq!(signature("puts", &[WzrdNodeDataType::String]), None);
+    }
+
+    #[test]
+    fn inputs_accept_narrower_values() {
+        assert!(WzrdNodeDataType::Number.accepts(&WzrdNodeDataType::Integer));
+        assert!(WzrdNodeDataType::Number.accepts(&WzrdNodeDataType::Float));
+        assert!(!WzrdNodeDataType::Integer.accepts(&WzrdNodeDataType::Number));
+        assert!(WzrdNodeDataType::Any.accepts(&WzrdNodeDataType::Regexp));
+        assert!(WzrdNodeDataType::Boolean.accepts(&WzrdNodeDataType::Any));
+        assert!(!WzrdNodeDataType::Boolean.accepts(&WzrdNodeDataType::String));
+    }
+
+    #[test]
+    fn conversions_are_only_offered_for_convertible_values() {
+        let conversion = |input: WzrdNodeDataType, value: WzrdNodeDataType| {
+            input.conversion_from(&value).map(|node| node.node().label)
+        };
+        assert_eq!(
+            conversion(WzrdNodeDataType::String, WzrdNodeDataType::Integer),
+            Some(WzrdNodes::ToS.node().label)
+        );
+        assert_eq!(
+            conversion(WzrdNodeDataType::Integer, WzrdNodeDataType::String),
+            Some(WzrdNodes::ToI.node().label)
+        );
+        assert_eq!(
+            conversion(WzrdNodeDataType::Number, WzrdNodeDataType::String),
+            Some(WzrdNodes::ToF.node().label)
+        );
+        assert_eq!(
+            conversion(WzrdNodeDataType::Integer, WzrdNodeDataType::Boolean),
+            None
+        );
+        assert_eq!(
+            conversion(WzrdNodeDataType::Number, WzrdNodeDataType::Integer),
+            None
+        );
+    }
+
+    #[test]
+    fn connections_are_checked_before_they_are_made() {
+        let mut editor = WzrdNodeGraph::new();
+        let mut add_node = |node: WzrdNodes| {
+            let template = node.node();
+            editor.state.graph.add_node(
+                template.label.clone(),
+                template.user_data(&mut editor.user_state),
+                |inner, node_id| template.build_node(inner, &mut Default::default(), node_id),
+            )
+        };
+        let upcase = add_node(WzrdNodes::Upcase);
+        let downcase = add_node(WzrdNodes::Downcase);
+        let sum = add_node(WzrdNodes::Add);
+        let difference = add_node(WzrdNodes::Subtract);
+        let negation = add_node(WzrdNodes::Not);
+        let graph = &mut editor.state.graph;
+        let output = |graph: &WzrdGraph, node_id: NodeId| graph[node_id].outputs[0].1;
+        let input = |graph: &WzrdGraph, node_id: NodeId| graph[node_id].inputs[0].1;
+
+        let check =
+            WzrdConnectionCheck::new(graph, output(graph, upcase), input(graph, difference));
+        assert!(matches!(
+            check,
+            WzrdConnectionCheck::Convertible(WzrdNodes::ToF)
+        ));
+
+        let negated = output(graph, negation);
+        graph.outputs[negated].typ = WzrdNodeDataType::Boolean;
+        let check = WzrdConnectionCheck::new(graph, negated, input(graph, difference));
+        assert!(check == WzrdConnectionCheck::Invalid);
+
+        graph.add_connection(negated, input(graph, difference));
+        let check =
+            WzrdConnectionCheck::new(graph, output(graph, difference), input(graph, negation));
+        assert!(check == WzrdConnectionCheck::Cycle);
+        let check =
+            WzrdConnectionCheck::new(graph, output(graph, difference), input(graph, upcase));
+        assert!(check == WzrdConnectionCheck::Valid);
+
+        // strings are joined with `+`
+        let joined = [(upcase, 0), (downcase, 1)];
+        for (node_id, index) in joined {
+            let (value, sum_input) = (output(graph, node_id), graph[sum].inputs[index].1);
+            let check = WzrdConnectionCheck::new(graph, value, sum_input);
+            assert!(check == WzrdConnectionCheck::Valid);
+            graph.add_connection(value, sum_input);
+        }
+        editor.infer_types();
+        let joined = editor.state.graph[sum].outputs[0].1;
+        assert_eq!(
+            editor.state.graph.outputs[joined].typ,
+            WzrdNodeDataType::String
+        );
+    }
+}
@@ -1,8 +1,9 @@
-#[cfg(target_arch = "wasm32")]
-mod app;
+pub mod app;
+#[cfg(all(not(target_arch = "wasm32"), feature = "persistence"))]
+pub mod cli;
+pub mod graph_core;
 
-#[cfg(target_arch = "wasm32")]
-pub use app::wzrd_node_graph::WzrdNodeGraph;
+pub use graph_core::wzrd_node_graph::WzrdNodeGraph;
 
 #[cfg(target_arch = "wasm32")]
 use eframe::{
@@ -1,18 +1,31 @@
-use eframe::egui::Visuals;
 #[cfg(target_arch = "wasm32")]
 use eframe::wasm_bindgen::{self, prelude::*};
-use log::info;
-use std::ops::Deref;
 
-use crate::app::wzrd_node_graph::WzrdNodeGraph;
-
-mod app;
+#[cfg(feature = "persistence")]
+use graph_editor::app::stored_layout;
+use graph_editor::app::WzrdApp;
+use graph_editor::graph_core::node::custom::CUSTOM_NODES_FILE;
+use graph_editor::WzrdNodeGraph;
 
 #[wasm_bindgen]
 #[cfg(target_arch = "wasm32")]
 extern "C" {
     #[wasm_bindgen(js_name = "getFileContents")]
     pub fn get_file_contents() -> String;
+
+    #[wasm_bindgen(catch, js_name = "getCustomNodes")]
+    pub fn get_custom_nodes() -> Result<String, JsValue>;
+
+    /// JSON array of `{ "path": ..., "contents": ... }` for the other ruby files in the workspace.
+    #[wasm_bindgen(catch, js_name = "getWorkspaceFiles")]
+    pub fn get_workspace_files() -> Result<String, JsValue>;
+}
+
+#[cfg(target_arch = "wasm32")]
+#[derive(serde::Deserialize)]
+struct WorkspaceFile {
+    path: String,
+    contents: String,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -20,10 +33,11 @@ fn main() {
     env_logger::init();
     use eframe::egui::Visuals;
 
-    let app = WzrdNodeGraph::new();
-    let format_requested = app.format_requested.clone();
+    let mut app = WzrdNodeGraph::new();
 
-    *format_requested.lock().unwrap() = true;
+    if let Ok(custom_nodes) = std::fs::read_to_string(CUSTOM_NODES_FILE) {
+        app.load_custom_nodes(&custom_nodes);
+    }
 
     eframe::run_native(
         "Wzrd Node Graph",
@@ -32,41 +46,35 @@ fn main() {
             creation_context.egui_ctx.set_visuals(Visuals::dark());
             #[cfg(feature = "persistence")]
             {
-                let mut wzrd_graph = Box::new(app);
-                let mut graph = wzrd_graph.state.graph.clone();
-                let mut user_state = wzrd_graph.user_state.clone();
-                wzrd_graph.initialize_graph(
-                    &mut graph,
-                    &mut user_state,
-                    // "
-                    // def main(a)
-                    //    return (48*(11+a))
-                    // end
-                    //                 ",
-                    "
+                let mut wzrd_graph = app;
+                // "
+                // def main(a)
+                //    return (48*(11+a))
+                // end
+                //                 ",
+                //                     "
+                // def main
+                //    (15*7)
+                //    return (48*(11+20))
+                // end
+                //                 ",
+                let code = "
                     def main(a)
                         return (a%2==0) ? (5+5) : (48*(11+a))
                     end
-                                        ",
-                    //                     "
-                    // def main
-                    //    (15*7)
-                    //    return (48*(11+20))
-                    // end
-                    //                 ",
-                );
-                wzrd_graph.user_state = user_state;
-                wzrd_graph.state.graph = graph;
-                wzrd_graph
+                                        ";
+                wzrd_graph.load_document(code, stored_layout(creation_context.storage, code));
+                Box::new(WzrdApp::new(wzrd_graph))
             }
 
             #[cfg(not(feature = "persistence"))]
-            Box::new(WzrdNodeGraph::default())
+            Box::new(WzrdApp::new(WzrdNodeGraph::default()))
         }),
     )
     .unwrap();
 }
 
+#[cfg(target_arch = "wasm32")]
 pub struct GraphWrapper(WzrdNodeGraph);
 
 #[cfg(target_arch = "wasm32")]
@@ -76,27 +84,40 @@ fn main() {
 
     let web_options = eframe::WebOptions::default();
 
-    let wrapper = GraphWrapper(WzrdNodeGraph::new());
-    let format_requested = wrapper.0.format_requested.clone();
+    let mut wrapper = GraphWrapper(WzrdNodeGraph::new());
+
+    // hosts that do not know custom nodes may not provide them
+    let custom_nodes = get_custom_nodes().unwrap_or_default();
+    if !custom_nodes.is_empty() {
+        wrapper.0.load_custom_nodes(&custom_nodes);
+    }
 
     wasm_bindgen_futures::spawn_local(async {
         eframe::start_web(
             "WzrdGraphEditor",
             web_options,
             Box::new(|cc| {
-                let mut wzrd_graph = Box::new(wrapper.0);
-                let mut graph = wzrd_graph.state.graph.clone();
+                let mut wzrd_graph = wrapper.0;
                 let mut user_state = wzrd_graph.user_state.clone();
-                wzrd_graph.initialize_graph(&mut graph, &mut user_state, &get_file_contents());
+                let workspace_files: Vec<WorkspaceFile> = get_workspace_files()
+                    .ok()
+                    .and_then(|files| serde_json::from_str(&files).ok())
+                    .unwrap_or_default();
+                for file in workspace_files {
+                    wzrd_graph.index_project_methods(
+                        &mut user_state,
+                        Some(&file.path),
+                        &file.contents,
+                    );
+                }
                 wzrd_graph.user_state = user_state;
-                wzrd_graph.state.graph = graph;
+                let code = get_file_contents();
+                wzrd_graph.load_document(&code, stored_layout(cc.storage, &code));
 
-                wzrd_graph
+                Box::new(WzrdApp::new(wzrd_graph))
             }),
         )
         .await
         .expect("failed to start eframe");
     });
-
-    *format_requested.lock().unwrap() = true;
 }
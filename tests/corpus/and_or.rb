@@ -0,0 +1,4 @@
+a = true
+b = false
+puts a && b
+puts a || b
@@ -0,0 +1,5 @@
+require "x"
+log("start")
+sleep 1
+foo 1, 2
+bar 3
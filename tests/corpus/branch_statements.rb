@@ -0,0 +1,14 @@
+def foo(a, c)
+  if c
+    puts a
+    x = a + 1
+    return x
+  else
+    puts 2
+  end
+  y = if a
+    puts 3
+    4
+  end
+  y
+end
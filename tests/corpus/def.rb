@@ -0,0 +1,3 @@
+def greet(name)
+    puts name
+end
@@ -0,0 +1,4 @@
+class A
+  def main
+  end
+end
@@ -0,0 +1,2 @@
+big = 10 > 5
+"big" if big
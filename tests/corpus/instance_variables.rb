@@ -0,0 +1,2 @@
+@count = 1
+puts @count
@@ -0,0 +1,3 @@
+puts true
+puts false
+puts nil
@@ -0,0 +1,8 @@
+puts 42
+puts 1_000
+puts -0x2A
+puts 017
+puts 123456789012345678901234567890
+puts 1.5
+puts "text"
+puts :symbol
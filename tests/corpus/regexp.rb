@@ -0,0 +1,3 @@
+puts /wz+rd/
+puts %r{wz/rd}
+puts /wz\/rd/i
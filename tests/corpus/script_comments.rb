@@ -0,0 +1,9 @@
+#!/usr/bin/env ruby
+# frozen_string_literal: true
+
+def greet(name)
+  # says hello
+  puts name
+end
+
+greet("world")
@@ -0,0 +1 @@
+self.puts 1
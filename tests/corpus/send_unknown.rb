@@ -0,0 +1,6 @@
+name = "wzrd"
+puts name.frobnicate(1, 2)
+puts upcase(name)
+puts name.gsub("w")
+output(name)
+puts name <=> "x"
@@ -0,0 +1,2 @@
+big = 10 > 5
+puts big ? "big" : "small"
@@ -0,0 +1,11 @@
+class Greeter
+  def helper(a)
+      a.upcase
+  end
+
+  def greet(name)
+    puts helper(name)
+    x = name.size
+    x
+  end
+end
@@ -0,0 +1,3 @@
+def main(a, b = 2, *rest, k: 1, **options, &block)
+    puts (a+b)
+end
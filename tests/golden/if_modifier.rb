@@ -0,0 +1,4 @@
+big = (10>5)
+if big
+    "big"
+end
\ No newline at end of file
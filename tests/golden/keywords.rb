@@ -0,0 +1,3 @@
+puts true
+puts false
+puts nil
\ No newline at end of file
@@ -0,0 +1,8 @@
+puts 42
+puts 1000
+puts -42
+puts 15
+puts 123456789012345678901234567890
+puts 1.5
+puts "text"
+puts :symbol
\ No newline at end of file
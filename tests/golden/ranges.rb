@@ -0,0 +1,6 @@
+puts (1..3)
+puts (1...3)
+puts ("a".."z")
+puts (1..)
+puts (..3)
+puts (16...3)
\ No newline at end of file
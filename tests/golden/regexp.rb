@@ -0,0 +1,3 @@
+puts /wz+rd/
+puts /wz\/rd/
+puts /wz\/rd/i
\ No newline at end of file
@@ -0,0 +1,5 @@
+def double(a)
+  a * 2
+end
+
+puts double(3)
\ No newline at end of file
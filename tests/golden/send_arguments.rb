@@ -0,0 +1,5 @@
+a = 1
+helper(a, k: 1)
+log({ level: a })
+puts [1, 2].first
+puts "sum #{a}"
\ No newline at end of file
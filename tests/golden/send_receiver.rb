@@ -0,0 +1,2 @@
+name = "wzrd"
+puts name.upcase
\ No newline at end of file
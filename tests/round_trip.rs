@@ -0,0 +1,86 @@
+//! Runs the Ruby snippets in `tests/corpus` through the graph and back. The outline of each
+//! graph and the generated code are compared with the files in `tests/golden`; run with
+//! `UPDATE_GOLDEN=1` to rewrite them after an intended change.
+
+use graph_editor::graph_core::{check_round_trip, generate_ruby, import_ruby, RoundTrip};
+use std::path::{Path, PathBuf};
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// The snippets of the corpus with their names, sorted so failures show up in a stable order.
+fn corpus() -> Vec<(String, String)> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(tests_dir().join("corpus"))
+        .expect("the corpus directory is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rb"))
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let code = std::fs::read_to_string(&path).unwrap();
+            (name, code)
+        })
+        .collect()
+}
+
+/// Golden files are only written with `UPDATE_GOLDEN` set, a missing one is a failure like a
+/// differing one.
+fn compare_golden(path: &Path, actual: &str, failures: &mut Vec<String>) {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, actual).unwrap();
+        return;
+    }
+    match std::fs::read_to_string(path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) => failures.push(format!(
+            "{:} differs\n--- expected\n{expected}\n--- actual\n{actual}",
+            path.display()
+        )),
+        Err(_) => failures.push(format!(
+            "{:} is missing, run with UPDATE_GOLDEN=1 to write it\n--- actual\n{actual}",
+            path.display()
+        )),
+    }
+}
+
+#[test]
+fn corpus_matches_golden_files() {
+    let golden = tests_dir().join("golden");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(&golden).unwrap();
+    }
+
+    let mut failures = vec![];
+    for (name, code) in corpus() {
+        let mut graph = import_ruby(&code);
+        compare_golden(
+            &golden.join(format!("{name}.graph")),
+            &format!("{}\n", graph.outline()),
+            &mut failures,
+        );
+        compare_golden(
+            &golden.join(format!("{name}.rb")),
+            &generate_ruby(&mut graph).unwrap(),
+            &mut failures,
+        );
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn corpus_round_trips() {
+    let mut failures = vec![];
+    for (name, code) in corpus() {
+        let RoundTrip { problems, .. } = check_round_trip(&code);
+        failures.extend(
+            problems
+                .into_iter()
+                .map(|problem| format!("{name}: {problem}")),
+        );
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}